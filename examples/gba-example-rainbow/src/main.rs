@@ -5,9 +5,10 @@ use gba_core::{
     graphics::{
         bitmap::{BitmapMode, Mode4},
         helpers::rainbow2,
-        Graphics,
+        wait_for_vblank, Graphics,
     },
     input::{Keys, IO},
+    interrupt,
 };
 
 fn main() {
@@ -17,6 +18,8 @@ fn main() {
 
     let mut width = 10;
 
+    interrupt::enable_master();
+
     loop {
         io.update();
 
@@ -29,7 +32,7 @@ fn main() {
         g.clear();
         rainbow2(g, width);
 
-        while g.vblank() {}
+        wait_for_vblank();
         g.swap();
     }
 }
@@ -0,0 +1,28 @@
+//! BIOS calls
+
+/// Halt the CPU until the next VBlank interrupt (BIOS `VBlankIntrWait`).
+///
+/// The VBlank interrupt and the master interrupt enable must both be set,
+/// otherwise this never returns.
+#[cfg(target_os = "none")]
+pub fn vblank_intr_wait() {
+    // SWI comments are encoded differently in thumb and ARM state
+    #[cfg(target_feature = "thumb-mode")]
+    unsafe {
+        asm!("swi 0x05" ::: "r0", "r1", "r2", "r3" : "volatile")
+    }
+    #[cfg(not(target_feature = "thumb-mode"))]
+    unsafe {
+        asm!("swi 0x050000" ::: "r0", "r1", "r2", "r3" : "volatile")
+    }
+}
+
+/// Wait until the start of the next VBlank by polling DISPSTAT, for builds
+/// without the BIOS.
+#[cfg(not(target_os = "none"))]
+pub fn vblank_intr_wait() {
+    use gba::io::display::DISPSTAT;
+
+    while DISPSTAT.read().vblank_flag() {}
+    while !DISPSTAT.read().vblank_flag() {}
+}
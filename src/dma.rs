@@ -0,0 +1,185 @@
+//! Direct memory access (DMA) transfers
+
+use core::ptr;
+
+use crate::memory::{
+    REG_DMA0CNT_H, REG_DMA0CNT_L, REG_DMA0DAD, REG_DMA0SAD, REG_DMA1CNT_H, REG_DMA1CNT_L,
+    REG_DMA1DAD, REG_DMA1SAD, REG_DMA2CNT_H, REG_DMA2CNT_L, REG_DMA2DAD, REG_DMA2SAD,
+    REG_DMA3CNT_H, REG_DMA3CNT_L, REG_DMA3DAD, REG_DMA3SAD,
+};
+
+/// Channel registers as `(source, destination, count, control)`
+const REGISTERS: [(usize, usize, usize, usize); 4] = [
+    (REG_DMA0SAD, REG_DMA0DAD, REG_DMA0CNT_L, REG_DMA0CNT_H),
+    (REG_DMA1SAD, REG_DMA1DAD, REG_DMA1CNT_L, REG_DMA1CNT_H),
+    (REG_DMA2SAD, REG_DMA2DAD, REG_DMA2CNT_L, REG_DMA2CNT_H),
+    (REG_DMA3SAD, REG_DMA3DAD, REG_DMA3CNT_L, REG_DMA3CNT_H),
+];
+
+/// Maximum number of units per DMA 3 transfer used by the bulk helpers
+const CHUNK: usize = 0x8000;
+
+/// DMA channels, in order of priority
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Channel {
+    /// Highest priority, used for time critical (HBlank) transfers
+    Dma0 = 0,
+    /// Used for sound FIFO transfers
+    Dma1 = 1,
+    /// Used for sound FIFO transfers
+    Dma2 = 2,
+    /// Lowest priority, used for general purpose copies
+    Dma3 = 3,
+}
+
+/// Source or destination address adjustment after each unit
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AddressControl {
+    Increment = 0,
+    Decrement = 1,
+    Fixed = 2,
+    /// Increment, resetting to the initial address on each repeat
+    /// (destination only)
+    IncrementReload = 3,
+}
+
+/// DMA start timing
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Timing {
+    Immediate = 0,
+    VBlank = 1,
+    HBlank = 2,
+    Special = 3,
+}
+
+/// DMA control (DMAxCNT_H) setting
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DmaControl(u16);
+
+impl DmaControl {
+    /// Create a new DMA control setting, with all fields zeroed
+    pub const fn new() -> DmaControl {
+        DmaControl(0)
+    }
+
+    /// Set the destination address control
+    pub const fn with_dest(self, c: AddressControl) -> DmaControl {
+        DmaControl((self.0 & !0x0060) | ((c as u16) << 5))
+    }
+
+    /// Set the source address control
+    pub const fn with_source(self, c: AddressControl) -> DmaControl {
+        DmaControl((self.0 & !0x0180) | ((c as u16) << 7))
+    }
+
+    /// Repeat the transfer on every trigger (VBlank / HBlank / special)
+    pub const fn with_repeat(self, repeat: bool) -> DmaControl {
+        DmaControl((self.0 & !0x0200) | ((repeat as u16) << 9))
+    }
+
+    /// Transfer 32-bit words rather than 16-bit halfwords
+    pub const fn with_32bit(self, wide: bool) -> DmaControl {
+        DmaControl((self.0 & !0x0400) | ((wide as u16) << 10))
+    }
+
+    /// Set the start timing
+    pub const fn with_timing(self, t: Timing) -> DmaControl {
+        DmaControl((self.0 & !0x3000) | ((t as u16) << 12))
+    }
+
+    /// Raise the DMA interrupt on completion
+    pub const fn with_irq(self, irq: bool) -> DmaControl {
+        DmaControl((self.0 & !0x4000) | ((irq as u16) << 14))
+    }
+
+    /// Enable the channel
+    pub const fn with_enable(self, enable: bool) -> DmaControl {
+        DmaControl((self.0 & !0x8000) | ((enable as u16) << 15))
+    }
+
+    /// Fetch the raw register value
+    pub const fn bits(&self) -> u16 {
+        self.0
+    }
+}
+
+impl Channel {
+    /// Start a transfer of `count` units from `src` to `dst`.
+    ///
+    /// The enable bit is set automatically. Immediate transfers halt the CPU
+    /// until they complete.
+    pub unsafe fn start(self, src: *const u8, dst: *mut u8, count: u16, control: DmaControl) {
+        let (sad, dad, cnt_l, cnt_h) = REGISTERS[self as usize];
+        self.stop();
+        ptr::write_volatile(sad as *mut u32, src as u32);
+        ptr::write_volatile(dad as *mut u32, dst as u32);
+        ptr::write_volatile(cnt_l as *mut u16, count);
+        ptr::write_volatile(cnt_h as *mut u16, control.with_enable(true).0);
+    }
+
+    /// Stop any transfer on this channel.
+    pub fn stop(self) {
+        let (_, _, _, cnt_h) = REGISTERS[self as usize];
+        unsafe { ptr::write_volatile(cnt_h as *mut u16, 0) };
+    }
+
+    /// Check whether this channel is enabled (transferring or waiting for a
+    /// trigger)
+    pub fn is_enabled(self) -> bool {
+        let (_, _, _, cnt_h) = REGISTERS[self as usize];
+        unsafe { ptr::read_volatile(cnt_h as *const u16) & 0x8000 != 0 }
+    }
+}
+
+/// Copy `count` 32-bit words from `src` to `dst` using DMA 3.
+pub unsafe fn copy32(src: *const u32, dst: *mut u32, count: usize) {
+    let control = DmaControl::new().with_32bit(true);
+    let mut i = 0;
+    while i < count {
+        let n = core::cmp::min(count - i, CHUNK);
+        Channel::Dma3.start(
+            src.add(i) as *const u8,
+            dst.add(i) as *mut u8,
+            n as u16,
+            control,
+        );
+        i += n;
+    }
+}
+
+/// Copy `count` 16-bit halfwords from `src` to `dst` using DMA 3.
+pub unsafe fn copy16(src: *const u16, dst: *mut u16, count: usize) {
+    let control = DmaControl::new();
+    let mut i = 0;
+    while i < count {
+        let n = core::cmp::min(count - i, CHUNK);
+        Channel::Dma3.start(
+            src.add(i) as *const u8,
+            dst.add(i) as *mut u8,
+            n as u16,
+            control,
+        );
+        i += n;
+    }
+}
+
+/// Fill `count` 32-bit words at `dst` with `value` using DMA 3.
+pub unsafe fn fill32(value: u32, dst: *mut u32, count: usize) {
+    // The CPU is halted during immediate transfers, so the source may live
+    // on the stack.
+    let v = value;
+    let control = DmaControl::new()
+        .with_32bit(true)
+        .with_source(AddressControl::Fixed);
+    let mut i = 0;
+    while i < count {
+        let n = core::cmp::min(count - i, CHUNK);
+        Channel::Dma3.start(
+            &v as *const u32 as *const u8,
+            dst.add(i) as *mut u8,
+            n as u16,
+            control,
+        );
+        i += n;
+    }
+}
@@ -4,21 +4,35 @@
 .align
 .arm
 
-@ isr stub in arm (32-bit) assembly to jump to actual ISR handler in 16-bit mode
+@ isr stub in arm (32-bit) assembly, called by the BIOS with r0 = REG_BASE
+@ Interrupts stay masked (CPSR.I set) throughout, so handlers are not nested
 _isr_stub:
+    @ Acknowledge pending interrupts
+    add r0, r0, #0x200          @ r0 = &REG_IE
+    ldr r1, [r0]                @ r1 = IF << 16 | IE
+    and r1, r1, r1, lsr #16     @ r1 = IE & IF
+    strh r1, [r0, #2]           @ Write IE & IF to IF
+
+    @ Clear IME, saving the previous value
+    ldrh r3, [r0, #8]           @ r3 = IME
+    mov r2, #0
+    strh r2, [r0, #8]           @ IME = 0
+
     @ Push stack frame
     mrs r2, spsr                @ Move SPSR to r2
-    stmfd sp!, {r2-r3, ip, lr}  @ Push sprs, IME, (IE,IF), lr_irq to stack
+    stmfd sp!, {r2-r3, ip, lr}  @ Push sprs, IME, ip, lr_irq to stack
 
-    @ Set mode to usr
+    @ Set mode to sys, with IRQs still masked
     mrs r3, cpsr                @ Move CPSR to R3
     bic r3, r3, #0xDF
-    orr r3, r3, #0x1F
+    orr r3, r3, #0x9F
     msr cpsr, r3                @ Load CPSR from R3
 
     @ Launch isr
     stmfd sp!, {r0,lr}          @ Push &REG_IE, lr_sys to stack
-    ldr r3, = isr_master        @ Load thumb address
+    mov r0, r1                  @ Pass IE & IF to isr_master
+    ldr r3, = isr_master        @ Load isr_master address
+    mov lr, pc                  @ Return to the following ldmfd
     bx r3                       @ Jump!
     ldmfd sp!, {r0,lr}          @ Pop &REG_IE, lr_sys from stack
 
@@ -29,6 +43,7 @@ _isr_stub:
     msr cpsr, r3                @ Load CPSR from R3
 
     @ Pop stack frame
-    ldmfd sp!, {r2-r3, ip, lr}  @ Pop sprs, IME, (IE,IF), lr_irq from stack
+    ldmfd sp!, {r2-r3, ip, lr}  @ Pop sprs, IME, ip, lr_irq from stack
     msr spsr, r2                @ Load SPSR from r2
-    bx lr                       @ Return to caller (bios?)
+    strh r3, [r0, #8]           @ Restore IME
+    bx lr                       @ Return to the BIOS
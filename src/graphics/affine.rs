@@ -0,0 +1,178 @@
+//! Affine transformations for sprites and backgrounds
+//!
+//! Angles are expressed as a `u16` fraction of a full turn (`0x4000` is 90
+//! degrees), trigonometric results are 4.12 fixed point, and affine
+//! parameters and scale factors are 8.8 fixed point.
+
+/// One (1.0) in 4.12 fixed point, as returned by [`sin`] and [`cos`]
+pub const ONE_12: i32 = 1 << 12;
+
+/// One (1.0) in 8.8 fixed point, as used for affine parameters and scales
+pub const ONE_8: i32 = 1 << 8;
+
+/// First quadrant of a 256 step sine table, in 4.12 fixed point
+const SIN_QUARTER: [i16; 65] = [
+    0, 101, 201, 301, 401, 501, 601, 700, 799, 897, 995, 1092, 1189, 1285, 1380, 1474, 1567, 1660,
+    1751, 1842, 1931, 2019, 2106, 2191, 2276, 2359, 2440, 2520, 2598, 2675, 2751, 2824, 2896, 2967,
+    3035, 3102, 3166, 3229, 3290, 3349, 3406, 3461, 3513, 3564, 3612, 3659, 3703, 3745, 3784, 3822,
+    3857, 3889, 3920, 3948, 3973, 3996, 4017, 4036, 4052, 4065, 4076, 4085, 4091, 4095, 4096,
+];
+
+/// Look up the sine table step `i` (of 256)
+fn sin_step(i: usize) -> i32 {
+    let i = i & 0xFF;
+    let r = i & 0x3F;
+    match i >> 6 {
+        0 => SIN_QUARTER[r] as i32,
+        1 => SIN_QUARTER[64 - r] as i32,
+        2 => -(SIN_QUARTER[r] as i32),
+        _ => -(SIN_QUARTER[64 - r] as i32),
+    }
+}
+
+/// Compute the sine of `angle` in 4.12 fixed point
+pub fn sin(angle: u16) -> i32 {
+    let i = (angle >> 8) as usize;
+    let frac = (angle & 0xFF) as i32;
+    let a = sin_step(i);
+    let b = sin_step(i + 1);
+    a + (((b - a) * frac) >> 8)
+}
+
+/// Compute the cosine of `angle` in 4.12 fixed point
+pub fn cos(angle: u16) -> i32 {
+    sin(angle.wrapping_add(0x4000))
+}
+
+/// Divide the 4.12 value `v` by the 8.8 `scale`, giving an 8.8 affine
+/// parameter
+fn inverse_scale(v: i32, scale: i32) -> i16 {
+    let scale = if scale == 0 { 1 } else { scale };
+    // 4.12 / 8.8 gives 8.4, so scale up by 4 bits for an 8.8 result
+    let p = (v << 4) / scale;
+    p.max(i16::MIN as i32).min(i16::MAX as i32) as i16
+}
+
+/// Affine transformation matrix, mapping screen space to texture space.
+///
+/// This is the inverse of the visible transformation, so a matrix that
+/// scales by 2.0 makes the sprite or background appear half the size.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AffineMatrix {
+    /// Texture x step per screen x (8.8 fixed point)
+    pub pa: i16,
+    /// Texture x step per screen y (8.8 fixed point)
+    pub pb: i16,
+    /// Texture y step per screen x (8.8 fixed point)
+    pub pc: i16,
+    /// Texture y step per screen y (8.8 fixed point)
+    pub pd: i16,
+}
+
+impl AffineMatrix {
+    /// Create a new matrix from raw 8.8 fixed point parameters
+    pub const fn new(pa: i16, pb: i16, pc: i16, pd: i16) -> AffineMatrix {
+        AffineMatrix { pa, pb, pc, pd }
+    }
+
+    /// The identity matrix
+    pub const fn identity() -> AffineMatrix {
+        AffineMatrix::new(ONE_8 as i16, 0, 0, ONE_8 as i16)
+    }
+
+    /// Create a matrix that displays a sprite or background rotated
+    /// counter-clockwise by `angle` and scaled by `(scale_x, scale_y)`
+    /// (8.8 fixed point, `0x100` is the original size).
+    ///
+    /// A scale of 0 is treated as the smallest non-zero scale, and parameters
+    /// saturate at the limits of the affine registers.
+    pub fn rotate_scale(angle: u16, scale_x: i32, scale_y: i32) -> AffineMatrix {
+        let (s, c) = (sin(angle), cos(angle));
+        AffineMatrix {
+            pa: inverse_scale(c, scale_x),
+            pb: inverse_scale(-s, scale_x),
+            pc: inverse_scale(s, scale_y),
+            pd: inverse_scale(c, scale_y),
+        }
+    }
+
+    /// Create a matrix that rotates counter-clockwise by `angle`
+    pub fn rotate(angle: u16) -> AffineMatrix {
+        AffineMatrix::rotate_scale(angle, ONE_8, ONE_8)
+    }
+
+    /// Create a matrix that scales by `(scale_x, scale_y)` (8.8 fixed point)
+    pub fn scale(scale_x: i32, scale_y: i32) -> AffineMatrix {
+        AffineMatrix::rotate_scale(0, scale_x, scale_y)
+    }
+
+    /// Compute the matrix product `self * other`
+    pub fn multiply(&self, other: &AffineMatrix) -> AffineMatrix {
+        let (a, b) = (self, other);
+        AffineMatrix {
+            pa: ((a.pa as i32 * b.pa as i32 + a.pb as i32 * b.pc as i32) >> 8) as i16,
+            pb: ((a.pa as i32 * b.pb as i32 + a.pb as i32 * b.pd as i32) >> 8) as i16,
+            pc: ((a.pc as i32 * b.pa as i32 + a.pd as i32 * b.pc as i32) >> 8) as i16,
+            pd: ((a.pc as i32 * b.pb as i32 + a.pd as i32 * b.pd as i32) >> 8) as i16,
+        }
+    }
+
+    /// Transform the screen space offset `(x, y)` into texture space,
+    /// with inputs and outputs in matching fixed point formats
+    pub fn apply(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            (self.pa as i32 * x + self.pb as i32 * y) >> 8,
+            (self.pc as i32 * x + self.pd as i32 * y) >> 8,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sin_at_quadrants() {
+        assert_eq!(sin(0), 0);
+        assert_eq!(sin(0x4000), ONE_12);
+        assert_eq!(sin(0x8000), 0);
+        assert_eq!(sin(0xC000), -ONE_12);
+        assert_eq!(cos(0), ONE_12);
+        assert_eq!(cos(0x4000), 0);
+        assert_eq!(cos(0x8000), -ONE_12);
+        assert_eq!(cos(0xC000), 0);
+    }
+
+    #[test]
+    fn sin_wraps_at_full_turn() {
+        // The last step interpolates back towards sin(0)
+        assert_eq!(sin(0xFFFF), -1);
+        assert_eq!(sin(0xFF00), -(SIN_QUARTER[1] as i32));
+        assert_eq!(sin(0x2000), sin(0x6000));
+        assert_eq!(sin(0xA000), -sin(0x2000));
+    }
+
+    #[test]
+    fn rotation_by_quarter_turns() {
+        assert_eq!(AffineMatrix::rotate(0), AffineMatrix::identity());
+        let quarter = AffineMatrix::rotate(0x4000);
+        assert_eq!(
+            quarter,
+            AffineMatrix::new(0, -ONE_8 as i16, ONE_8 as i16, 0)
+        );
+        assert_eq!(quarter.multiply(&quarter), AffineMatrix::rotate(0x8000));
+        assert_eq!(quarter.apply(ONE_8, 0), (0, ONE_8));
+    }
+
+    #[test]
+    fn scale_is_inverted_and_saturates() {
+        assert_eq!(
+            AffineMatrix::scale(0x200, 0x80),
+            AffineMatrix::new(0x80, 0, 0, 0x200)
+        );
+        let m = AffineMatrix::scale(0, 0);
+        assert_eq!((m.pa, m.pd), (i16::MAX, i16::MAX));
+        let m = AffineMatrix::rotate_scale(0x8000, 1, 1);
+        assert_eq!((m.pa, m.pd), (i16::MIN, i16::MIN));
+    }
+}
@@ -1,8 +1,15 @@
 //! Bitmap rendering modes
 
 use embedded_builder::region::Region;
-use gba::io::display::{DisplayControlSetting, DisplayMode, DISPCNT, DISPSTAT};
-
+use gba::io::display::{DisplayMode, DISPCNT, DISPSTAT};
+
+use crate::dma;
+use crate::graphics;
+use crate::graphics::affine::{AffineMatrix, ONE_8};
+use crate::graphics::dirty::{DirtyRects, Rect};
+use crate::graphics::objtiles::ObjTiles;
+use crate::graphics::sprite::ObjectMapping;
+use crate::graphics::tilemap::{self, Bg};
 use crate::memory::{IORAM, PALRAM, VRAM};
 
 /// Mode 3, 240x160@16bpp single buffer
@@ -35,6 +42,53 @@ pub trait BitmapMode<T> {
     /// modes.
     fn set(&mut self, x: usize, y: usize, c: T);
 
+    /// Get the colour of the pixel at `(x, y)`.
+    ///
+    /// This operates on the inactive buffer, for double-buffer rendering
+    /// modes.
+    fn get(&self, x: usize, y: usize) -> T;
+
+    /// Set the pixels from `(x0, y)` to `(x1, y)` inclusive to the colour
+    /// `c`.
+    ///
+    /// This operates on the inactive buffer, for double-buffer rendering
+    /// modes.
+    fn hline(&mut self, x0: usize, x1: usize, y: usize, c: T)
+    where
+        T: Copy,
+    {
+        for x in x0..=x1 {
+            self.set(x, y, c);
+        }
+    }
+
+    /// Set the pixels from `(x, y)` onwards to the colours in `row`.
+    ///
+    /// This operates on the inactive buffer, for double-buffer rendering
+    /// modes.
+    fn write_row(&mut self, x: usize, y: usize, row: &[T])
+    where
+        T: Copy,
+    {
+        for (i, c) in row.iter().enumerate() {
+            self.set(x + i, y, *c);
+        }
+    }
+
+    /// Fill the buffer with the colour `c`.
+    ///
+    /// This operates on the inactive buffer, for double-buffer rendering
+    /// modes.
+    fn fill(&mut self, c: T)
+    where
+        T: Copy,
+    {
+        let (w, h, _) = self.bounds();
+        for y in 0..h {
+            self.hline(0, w - 1, y, c);
+        }
+    }
+
     /// Clears the buffer.
     ///
     /// This operates on the inactive buffer, for double-buffer rendering
@@ -51,13 +105,31 @@ pub trait PalletMode<T> {
     fn get_pallet(&self, i: usize) -> T;
 }
 
+/// Mode 3 buffering options
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Buffering {
+    /// Draw directly to VRAM
+    Single,
+    /// Draw to the back buffer, with `swap` copying the whole frame to VRAM
+    Double,
+    /// Draw to the back buffer, with `swap` copying only the rows changed
+    /// since the last swap to VRAM
+    DirtyRows,
+}
+
 /// Graphics mode 3 - 240x160@16bpp, single-buffer
 ///
-/// Note: The `swap()` operation on a mode 3 graphics instance is a no-op.
+/// Note: The `swap()` operation on a mode 3 graphics instance is a no-op,
+/// unless software double buffering is enabled with `set_back_buffer` and
+/// `set_buffering`.
 #[derive(Debug, PartialEq)]
 pub struct Mode3 {
     ioram: Region<u16>,
     vram: Region<u16>,
+    obj_tiles: ObjTiles,
+    back: Option<&'static mut [u16]>,
+    buffering: Buffering,
+    dirty: [u32; MODE3.1 / 32],
 }
 
 impl BitmapMode<u16> for Mode3 {
@@ -65,6 +137,10 @@ impl BitmapMode<u16> for Mode3 {
         Mode3 {
             ioram: Region::from(IORAM),
             vram: Region::new(VRAM.0 + 0x0000, MODE3.0 * MODE3.1 * MODE3.2),
+            obj_tiles: ObjTiles::bitmap(ObjectMapping::OneD),
+            back: None,
+            buffering: Buffering::Single,
+            dirty: [0; MODE3.1 / 32],
         }
     }
 
@@ -73,27 +149,173 @@ impl BitmapMode<u16> for Mode3 {
     }
 
     fn swap(&mut self) {
-        // Mode 3 is not double-buffered.
+        let back = match self.back_buffer() {
+            Some(back) => back.as_ptr(),
+            None => return,
+        };
+
+        // Copy each run of changed rows with a single transfer
+        let mut y = 0;
+        while y < MODE3.1 {
+            if !self.is_dirty(y) {
+                y += 1;
+                continue;
+            }
+            let start = y;
+            while y < MODE3.1 && self.is_dirty(y) {
+                y += 1;
+            }
+            let i = start * MODE3.0;
+            unsafe {
+                let src = back.add(i) as *const u32;
+                let dst = (VRAM.0 + i * 2) as *mut u32;
+                dma::copy32(src, dst, (y - start) * MODE3.0 / 2);
+            }
+        }
+        self.dirty = [0; MODE3.1 / 32];
     }
 
     fn enable(&mut self) {
-        let dispcnt = DisplayControlSetting::new().with_mode(DisplayMode::Mode3);
+        let dispcnt = graphics::display_control(DisplayMode::Mode3).with_oam_memory_1d(true);
         DISPCNT.write(dispcnt);
+        reset_bg2();
     }
 
     fn set(&mut self, x: usize, y: usize, c: u16) {
-        self.vram.write_index(x + y * MODE3.0, c);
+        let i = x + y * MODE3.0;
+        match self.back_buffer() {
+            Some(back) => back[i] = c,
+            None => self.vram.write_index(i, c),
+        }
+        self.mark(y);
+    }
+
+    fn get(&self, x: usize, y: usize) -> u16 {
+        let i = x + y * MODE3.0;
+        match self.back {
+            Some(ref back) if self.buffering != Buffering::Single => back[i],
+            _ => *self.vram.read_index(i),
+        }
+    }
+
+    fn hline(&mut self, x0: usize, x1: usize, y: usize, c: u16) {
+        let (start, end) = (x0 + y * MODE3.0, x1 + y * MODE3.0);
+        match self.back_buffer() {
+            Some(back) => {
+                for p in back[start..=end].iter_mut() {
+                    *p = c;
+                }
+            }
+            None => {
+                for i in start..=end {
+                    self.vram.write_index(i, c);
+                }
+            }
+        }
+        self.mark(y);
+    }
+
+    fn write_row(&mut self, x: usize, y: usize, row: &[u16]) {
+        let base = x + y * MODE3.0;
+        match self.back_buffer() {
+            Some(back) => back[base..base + row.len()].copy_from_slice(row),
+            None => {
+                for (i, c) in row.iter().enumerate() {
+                    self.vram.write_index(base + i, *c);
+                }
+            }
+        }
+        self.mark(y);
+    }
+
+    fn fill(&mut self, c: u16) {
+        let v = c as u32 | (c as u32) << 16;
+        let dst = match self.back_buffer() {
+            Some(back) => back.as_mut_ptr() as *mut u32,
+            None => VRAM.0 as *mut u32,
+        };
+        unsafe { dma::fill32(v, dst, MODE3.0 * MODE3.1 / 2) };
+        self.dirty = [!0; MODE3.1 / 32];
     }
 
     fn clear(&mut self) {
-        for x in 0..self.bounds().0 {
-            for y in 0..self.bounds().1 {
-                self.set(x, y, 0);
+        self.fill(0);
+    }
+}
+
+impl Mode3 {
+    /// Fetch the object tiles usable in this mode (see `ObjTiles::bitmap`)
+    pub fn sprite_tiles(&mut self) -> &mut ObjTiles {
+        &mut self.obj_tiles
+    }
+
+    /// Supply the back buffer used for software double buffering.
+    ///
+    /// At 75 KB the buffer does not fit in IWRAM, so it is typically a
+    /// `static` placed in EWRAM with `#[link_section = ".ewram"]`. Programs
+    /// which never enable double buffering need not reserve one.
+    pub fn set_back_buffer(&mut self, buffer: &'static mut [u16; MODE3.0 * MODE3.1]) {
+        self.back = Some(buffer);
+        self.buffering = Buffering::Single;
+        self.dirty = [0; MODE3.1 / 32];
+    }
+
+    /// Set the buffering used for drawing.
+    ///
+    /// With double buffering, drawing operates on the back buffer supplied
+    /// with `set_back_buffer`, and `swap` copies it to VRAM by DMA, so should
+    /// be called during VBlank. The back buffer is initialised from the
+    /// current screen.
+    ///
+    /// Panics if double buffering is requested without a back buffer.
+    pub fn set_buffering(&mut self, buffering: Buffering) {
+        assert!(
+            buffering == Buffering::Single || self.back.is_some(),
+            "Mode3 double buffering needs a back buffer"
+        );
+        if self.buffering == Buffering::Single && buffering != Buffering::Single {
+            if let Some(ref mut back) = self.back {
+                let src = VRAM.0 as *const u32;
+                let dst = back.as_mut_ptr() as *mut u32;
+                unsafe { dma::copy32(src, dst, MODE3.0 * MODE3.1 / 2) };
             }
         }
+        self.buffering = buffering;
+        self.dirty = [0; MODE3.1 / 32];
+    }
+
+    /// Fetch the buffering used for drawing
+    pub fn buffering(&self) -> Buffering {
+        self.buffering
+    }
+
+    /// Fetch the back buffer, if drawing operates on it
+    fn back_buffer(&mut self) -> Option<&mut [u16]> {
+        match self.buffering {
+            Buffering::Single => None,
+            _ => self.back.as_mut().map(|b| &mut **b),
+        }
+    }
+
+    /// Record that the row `y` of the back buffer has changed
+    fn mark(&mut self, y: usize) {
+        self.dirty[y / 32] |= 1 << (y % 32);
+    }
+
+    /// Check whether the row `y` needs copying to VRAM on the next swap,
+    /// which is every row with full double buffering
+    fn is_dirty(&self, y: usize) -> bool {
+        self.buffering == Buffering::Double || self.dirty[y / 32] & (1 << (y % 32)) != 0
     }
 }
 
+/// Reset the BG2 affine matrix and reference point, which the bitmap modes
+/// use to place the frame on the screen, to display it unscaled
+fn reset_bg2() {
+    tilemap::write_matrix(Bg::BG2, &AffineMatrix::identity());
+    tilemap::write_reference(Bg::BG2, 0, 0);
+}
+
 /// Swap buffer enumeration
 #[derive(Debug, PartialEq)]
 enum SwapBuffer {
@@ -101,25 +323,176 @@ enum SwapBuffer {
     B,
 }
 
+/// Frame buffer pages of the double-buffered modes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Page {
+    /// The first page, at the start of VRAM
+    Frame0,
+    /// The second page, at offset `0xA000` in VRAM
+    Frame1,
+}
+
+/// Page flipping state shared by the double-buffered modes, with the regions
+/// changed by drawing in each page
+#[derive(Debug, PartialEq)]
+struct Pages {
+    vram: [Region<u16>; 2],
+    bounds: (usize, usize, usize),
+    active: SwapBuffer,
+    tracking: bool,
+    dirty: [DirtyRects; 2],
+}
+
+impl Pages {
+    /// Create the pages for a mode of the bounds `bounds`, displaying the
+    /// first page
+    fn new(bounds: (usize, usize, usize)) -> Pages {
+        let (width, height, depth) = bounds;
+        Pages {
+            vram: [
+                Region::new(Pages::address(0), width * height * depth / 8),
+                Region::new(Pages::address(1), width * height * depth / 8),
+            ],
+            bounds: bounds,
+            active: SwapBuffer::A,
+            tracking: false,
+            dirty: [DirtyRects::new(), DirtyRects::new()],
+        }
+    }
+
+    /// Fetch the address of the page with index `i`
+    fn address(i: usize) -> usize {
+        VRAM.0 + i * 0xA000
+    }
+
+    /// Display the inactive page, and draw to the other
+    fn swap(&mut self) {
+        self.active = match self.active {
+            SwapBuffer::A => SwapBuffer::B,
+            SwapBuffer::B => SwapBuffer::A,
+        };
+        DISPCNT.write(DISPCNT.read().with_frame1(self.active == SwapBuffer::B));
+    }
+
+    /// Fetch the index of the inactive (drawing) page
+    fn back_index(&self) -> usize {
+        match self.active {
+            SwapBuffer::A => 1,
+            SwapBuffer::B => 0,
+        }
+    }
+
+    /// Fetch the index of the active (displayed) page
+    fn front_index(&self) -> usize {
+        1 - self.back_index()
+    }
+
+    /// Fetch the page currently displayed
+    fn active_page(&self) -> Page {
+        match self.active {
+            SwapBuffer::A => Page::Frame0,
+            SwapBuffer::B => Page::Frame1,
+        }
+    }
+
+    /// Get the raw colour of the pixel at `(x, y)` in the page with index `i`
+    fn read(&self, i: usize, x: usize, y: usize) -> u16 {
+        let (width, _, depth) = self.bounds;
+        let n = x + y * width;
+        match depth {
+            8 => (*self.vram[i].read_index(n / 2) >> ((n % 2) * 8)) & 0x00FF,
+            _ => *self.vram[i].read_index(n),
+        }
+    }
+
+    /// Get the raw colour of the pixel at `(x, y)` in the inactive page
+    fn get_back(&self, x: usize, y: usize) -> u16 {
+        self.read(self.back_index(), x, y)
+    }
+
+    /// Get the raw colour of the pixel at `(x, y)` in the active page
+    fn get_front(&self, x: usize, y: usize) -> u16 {
+        self.read(self.front_index(), x, y)
+    }
+
+    /// Copy the whole of the active page into the inactive page, forgetting
+    /// any changed regions
+    fn copy_front_to_back(&mut self) {
+        let (width, height, depth) = self.bounds;
+        let src = Pages::address(self.front_index()) as *const u32;
+        let dst = Pages::address(self.back_index()) as *mut u32;
+        unsafe { dma::copy32(src, dst, width * height * depth / 32) };
+        self.dirty[0].clear();
+        self.dirty[1].clear();
+    }
+
+    /// Fetch the inactive (drawing) page for writing
+    fn back_mut(&mut self) -> &mut Region<u16> {
+        let i = self.back_index();
+        &mut self.vram[i]
+    }
+
+    /// Enable or disable tracking of changed regions, forgetting any
+    /// recorded so far
+    fn set_dirty_tracking(&mut self, enabled: bool) {
+        self.tracking = enabled;
+        self.dirty[0].clear();
+        self.dirty[1].clear();
+    }
+
+    /// Fetch the regions of the inactive page changed since it was last
+    /// brought up to date
+    fn dirty(&self) -> &DirtyRects {
+        &self.dirty[self.back_index()]
+    }
+
+    /// Record the region `r` of the inactive page as changed
+    fn mark(&mut self, r: Rect) {
+        if self.tracking {
+            let i = self.back_index();
+            self.dirty[i].add(r);
+        }
+    }
+
+    /// Copy the regions changed in the active page since it was last synced
+    /// into the inactive page
+    fn sync_back(&mut self) {
+        let (width, _, depth) = self.bounds;
+        let (front, back) = (self.front_index(), self.back_index());
+        let (src, dst) = (Pages::address(front), Pages::address(back));
+        // Copy whole halfwords, widening to halfword pixel boundaries
+        let per = 16 / depth;
+        for r in self.dirty[front].rects() {
+            let x = r.x - r.x % per;
+            let count = (r.x + r.width - x + per - 1) / per;
+            for y in r.y..r.y + r.height {
+                let offset = (x + y * width) * depth / 8;
+                unsafe {
+                    let src = (src + offset) as *const u16;
+                    dma::copy16(src, (dst + offset) as *mut u16, count);
+                }
+            }
+        }
+        self.dirty[front].clear();
+    }
+}
+
 /// Graphics mode 4 - 240x160@8bpp, double-buffered
 #[derive(Debug, PartialEq)]
 pub struct Mode4 {
     ioram: Region<u16>,
-    vram: [Region<u16>; 2],
+    pages: Pages,
     pallet: Region<u16>,
-    active: SwapBuffer,
+    obj_tiles: ObjTiles,
 }
 
 impl BitmapMode<u8> for Mode4 {
     fn new() -> Mode4 {
         Mode4 {
             ioram: Region::from(IORAM),
-            vram: [
-                Region::new(VRAM.0 + 0x0000, MODE4.0 * MODE4.1 * MODE4.2 / 8),
-                Region::new(VRAM.0 + 0xA000, MODE4.0 * MODE4.1 * MODE4.2 / 8),
-            ],
+            pages: Pages::new(MODE4),
             pallet: Region::new(PALRAM.0, 256),
-            active: SwapBuffer::A,
+            obj_tiles: ObjTiles::bitmap(ObjectMapping::OneD),
         }
     }
 
@@ -128,34 +501,22 @@ impl BitmapMode<u8> for Mode4 {
     }
 
     fn enable(&mut self) {
-        let dispcnt = DisplayControlSetting::new()
-            .with_mode(DisplayMode::Mode4)
+        let dispcnt = graphics::display_control(DisplayMode::Mode4)
             .with_bg2(true)
-            .with_frame1(true);
+            .with_frame1(self.active_page() == Page::Frame1)
+            .with_oam_memory_1d(true);
         DISPCNT.write(dispcnt);
+        reset_bg2();
     }
 
     fn swap(&mut self) {
-        let mut dispcnt = DISPCNT.read();
-        self.active = match self.active {
-            SwapBuffer::A => {
-                dispcnt = dispcnt.with_frame1(true);
-                SwapBuffer::B
-            }
-            SwapBuffer::B => {
-                dispcnt = dispcnt.with_frame1(false);
-                SwapBuffer::A
-            }
-        };
-        DISPCNT.write(dispcnt);
+        self.pages.swap();
     }
 
     fn set(&mut self, x: usize, y: usize, c: u8) {
-        let i = x + y * MODE3.0;
-        let vram = match self.active {
-            SwapBuffer::A => &mut self.vram[1],
-            SwapBuffer::B => &mut self.vram[0],
-        };
+        self.pages.mark(Rect::new(x, y, 1, 1));
+        let i = x + y * MODE4.0;
+        let vram = self.pages.back_mut();
         let mut v: u16 = *vram.read_index(i / 2);
         v = if i % 2 == 0 {
             (v & 0xFF00) | c as u16
@@ -165,12 +526,29 @@ impl BitmapMode<u8> for Mode4 {
         vram.write_index(i / 2, v);
     }
 
+    fn get(&self, x: usize, y: usize) -> u8 {
+        self.pages.get_back(x, y) as u8
+    }
+
+    fn hline(&mut self, x0: usize, x1: usize, y: usize, c: u8) {
+        self.pages.mark(Rect::new(x0, y, x1 + 1 - x0, 1));
+        self.write_span(x0 + y * MODE4.0, x1 + 1 - x0, |_| c);
+    }
+
+    fn write_row(&mut self, x: usize, y: usize, row: &[u8]) {
+        self.pages.mark(Rect::new(x, y, row.len(), 1));
+        self.write_span(x + y * MODE4.0, row.len(), |n| row[n]);
+    }
+
+    fn fill(&mut self, c: u8) {
+        let v = c as u32 * 0x0101_0101;
+        let dst = Pages::address(self.pages.back_index()) as *mut u32;
+        unsafe { dma::fill32(v, dst, MODE4.0 * MODE4.1 / 4) };
+        self.pages.mark(Rect::new(0, 0, MODE4.0, MODE4.1));
+    }
+
     fn clear(&mut self) {
-        for x in 0..self.bounds().0 {
-            for y in 0..self.bounds().1 {
-                self.set(x, y, 0);
-            }
-        }
+        self.fill(0);
     }
 }
 
@@ -185,28 +563,143 @@ impl PalletMode<u16> for Mode4 {
 }
 
 impl Mode4 {
+    /// Fetch the object tiles usable in this mode (see `ObjTiles::bitmap`)
+    pub fn sprite_tiles(&mut self) -> &mut ObjTiles {
+        &mut self.obj_tiles
+    }
+
+    /// Copy the `width` by `height` rectangle at `(x, y)` to `(dx, dy)`.
+    ///
+    /// This operates on the inactive buffer, and the rectangles may overlap.
+    pub fn copy_rect(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        dx: usize,
+        dy: usize,
+    ) {
+        let width = width
+            .min(MODE4.0.saturating_sub(x))
+            .min(MODE4.0.saturating_sub(dx));
+        let height = height
+            .min(MODE4.1.saturating_sub(y))
+            .min(MODE4.1.saturating_sub(dy));
+        let mut row = [0u8; MODE4.0];
+        for r in 0..height {
+            // Copy bottom up when moving down, so overlapping rows are read
+            // before they are overwritten
+            let r = if dy > y { height - 1 - r } else { r };
+            for (i, p) in row[..width].iter_mut().enumerate() {
+                *p = self.get(x + i, y + r);
+            }
+            self.write_row(dx, dy + r, &row[..width]);
+        }
+    }
+
+    /// Fetch the page currently displayed.
+    ///
+    /// Drawing operates on the other page, which is displayed after `swap`.
+    /// `enable` displays this page, so a new mode shows `Frame0` while
+    /// drawing to `Frame1`.
+    pub fn active_page(&self) -> Page {
+        self.pages.active_page()
+    }
+
+    /// Get the colour of the pixel at `(x, y)` in the displayed page
+    pub fn get_front(&self, x: usize, y: usize) -> u8 {
+        self.pages.get_front(x, y) as u8
+    }
+
+    /// Copy the whole of the displayed page into the inactive page, so that
+    /// drawing continues from the current frame.
+    pub fn copy_front_to_back(&mut self) {
+        self.pages.copy_front_to_back();
+    }
+
+    /// Enable or disable tracking of the regions changed by drawing, used by
+    /// `sync_back`
+    pub fn set_dirty_tracking(&mut self, enabled: bool) {
+        self.pages.set_dirty_tracking(enabled);
+    }
+
+    /// Fetch the regions of the inactive page changed since it was last
+    /// brought up to date
+    pub fn dirty(&self) -> &DirtyRects {
+        self.pages.dirty()
+    }
+
+    /// Bring the inactive page up to date with the active page, by copying
+    /// the regions changed in the active page since it was last synced.
+    ///
+    /// With dirty tracking enabled, calling this after each `swap` keeps
+    /// both pages identical, so only changes need drawing each frame.
+    pub fn sync_back(&mut self) {
+        self.pages.sync_back();
+    }
+
+    /// Write `len` pixels from the pixel index `i` in the inactive page,
+    /// where `pixel(n)` is the colour of the `n`th pixel.
+    ///
+    /// VRAM cannot take byte writes, so pairs of pixels are packed into
+    /// halfword stores, with a read-modify-write only for an unaligned
+    /// first or last pixel.
+    fn write_span<F: Fn(usize) -> u8>(&mut self, i: usize, len: usize, pixel: F) {
+        let vram = self.pages.back_mut();
+        let (mut i, end, mut n) = (i, i + len, 0);
+        if i % 2 == 1 && i < end {
+            let v: u16 = *vram.read_index(i / 2);
+            vram.write_index(i / 2, (v & 0x00FF) | (pixel(n) as u16) << 8);
+            i += 1;
+            n += 1;
+        }
+        while i + 1 < end {
+            vram.write_index(i / 2, pixel(n) as u16 | (pixel(n + 1) as u16) << 8);
+            i += 2;
+            n += 2;
+        }
+        if i < end {
+            let v: u16 = *vram.read_index(i / 2);
+            vram.write_index(i / 2, (v & 0xFF00) | pixel(n) as u16);
+        }
+    }
+
+    /// Check whether the display is in VBlank.
+    ///
+    /// To wait for VBlank without polling, use `graphics::wait_for_vblank`.
     pub fn vblank(&mut self) -> bool {
         DISPSTAT.read().vblank_flag()
     }
 }
 
+/// Mode 5 display scaling options
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Scaling {
+    /// Display the frame unscaled, in the top left corner of the screen
+    None,
+    /// Stretch the frame to fill the screen
+    Stretch,
+    /// Display the frame unscaled, centred on the screen
+    Centre,
+}
+
 /// Graphics mode 5 - 160x128@16bpp, double-buffered
 #[derive(Debug, PartialEq)]
 pub struct Mode5 {
     ioram: Region<u16>,
-    vram: [Region<u16>; 2],
-    active: SwapBuffer,
+    pages: Pages,
+    obj_tiles: ObjTiles,
+    scaling: Scaling,
 }
 
 impl BitmapMode<u16> for Mode5 {
     fn new() -> Mode5 {
         Mode5 {
             ioram: Region::from(IORAM),
-            vram: [
-                Region::new(VRAM.0 + 0x0000, MODE5.0 * MODE5.1 * MODE5.2 / 8),
-                Region::new(VRAM.0 + 0xA000, MODE5.0 * MODE5.1 * MODE5.2 / 8),
-            ],
-            active: SwapBuffer::A,
+            pages: Pages::new(MODE5),
+            obj_tiles: ObjTiles::bitmap(ObjectMapping::OneD),
+            scaling: Scaling::None,
         }
     }
 
@@ -215,44 +708,134 @@ impl BitmapMode<u16> for Mode5 {
     }
 
     fn enable(&mut self) {
-        let dispcnt = DisplayControlSetting::new()
-            .with_mode(DisplayMode::Mode5)
+        let dispcnt = graphics::display_control(DisplayMode::Mode5)
             .with_bg2(true)
-            .with_frame1(true);
+            .with_frame1(self.active_page() == Page::Frame1)
+            .with_oam_memory_1d(true);
         DISPCNT.write(dispcnt);
+        self.write_scaling();
     }
 
     fn swap(&mut self) {
-        let mut dispcnt = DISPCNT.read();
-        self.active = match self.active {
-            SwapBuffer::A => {
-                dispcnt = dispcnt.with_frame1(true);
-                SwapBuffer::B
-            }
-            SwapBuffer::B => {
-                dispcnt = dispcnt.with_frame1(false);
-                SwapBuffer::A
-            }
-        };
-        DISPCNT.write(dispcnt);
+        self.pages.swap();
     }
 
     fn set(&mut self, x: usize, y: usize, c: u16) {
-        match self.active {
-            SwapBuffer::A => {
-                self.vram[1].write_index(x + y * MODE5.0, c);
-            }
-            SwapBuffer::B => {
-                self.vram[0].write_index(x + y * MODE5.0, c);
-            }
-        };
+        self.pages.mark(Rect::new(x, y, 1, 1));
+        self.pages.back_mut().write_index(x + y * MODE5.0, c);
     }
 
-    fn clear(&mut self) {
-        for x in 0..self.bounds().0 {
-            for y in 0..self.bounds().1 {
-                self.set(x, y, 0);
-            }
+    fn get(&self, x: usize, y: usize) -> u16 {
+        self.pages.get_back(x, y)
+    }
+
+    fn hline(&mut self, x0: usize, x1: usize, y: usize, c: u16) {
+        self.pages.mark(Rect::new(x0, y, x1 + 1 - x0, 1));
+        let vram = self.pages.back_mut();
+        for i in x0 + y * MODE5.0..=x1 + y * MODE5.0 {
+            vram.write_index(i, c);
         }
     }
+
+    fn write_row(&mut self, x: usize, y: usize, row: &[u16]) {
+        self.pages.mark(Rect::new(x, y, row.len(), 1));
+        let vram = self.pages.back_mut();
+        let base = x + y * MODE5.0;
+        for (i, c) in row.iter().enumerate() {
+            vram.write_index(base + i, *c);
+        }
+    }
+
+    fn fill(&mut self, c: u16) {
+        let v = c as u32 | (c as u32) << 16;
+        let dst = Pages::address(self.pages.back_index()) as *mut u32;
+        unsafe { dma::fill32(v, dst, MODE5.0 * MODE5.1 / 2) };
+        self.pages.mark(Rect::new(0, 0, MODE5.0, MODE5.1));
+    }
+
+    fn clear(&mut self) {
+        self.fill(0);
+    }
+}
+
+impl Mode5 {
+    /// Fetch the object tiles usable in this mode (see `ObjTiles::bitmap`)
+    pub fn sprite_tiles(&mut self) -> &mut ObjTiles {
+        &mut self.obj_tiles
+    }
+
+    /// Set the scaling of the frame on the screen, applied immediately and
+    /// on `enable`
+    pub fn set_scaling(&mut self, scaling: Scaling) {
+        self.scaling = scaling;
+        self.write_scaling();
+    }
+
+    /// Fetch the scaling of the frame on the screen
+    pub fn scaling(&self) -> Scaling {
+        self.scaling
+    }
+
+    /// Write the BG2 affine matrix and reference point for the scaling
+    fn write_scaling(&mut self) {
+        // Screen to frame scale factors (8.8 fixed point) and frame offset
+        // (20.8 fixed point). Stretching rounds the scale factors up, so the
+        // last frame row and column are still displayed.
+        let (m, (x, y)) = match self.scaling {
+            Scaling::None => (AffineMatrix::identity(), (0, 0)),
+            Scaling::Stretch => (
+                AffineMatrix::new(
+                    (ONE_8 * 160 / 240 + 1) as i16,
+                    0,
+                    0,
+                    (ONE_8 * 128 / 160 + 1) as i16,
+                ),
+                (0, 0),
+            ),
+            Scaling::Centre => (AffineMatrix::identity(), (-40 << 8, -16 << 8)),
+        };
+        tilemap::write_matrix(Bg::BG2, &m);
+        tilemap::write_reference(Bg::BG2, x, y);
+    }
+
+    /// Fetch the page currently displayed.
+    ///
+    /// Drawing operates on the other page, which is displayed after `swap`.
+    /// `enable` displays this page, so a new mode shows `Frame0` while
+    /// drawing to `Frame1`.
+    pub fn active_page(&self) -> Page {
+        self.pages.active_page()
+    }
+
+    /// Get the colour of the pixel at `(x, y)` in the displayed page
+    pub fn get_front(&self, x: usize, y: usize) -> u16 {
+        self.pages.get_front(x, y)
+    }
+
+    /// Copy the whole of the displayed page into the inactive page, so that
+    /// drawing continues from the current frame.
+    pub fn copy_front_to_back(&mut self) {
+        self.pages.copy_front_to_back();
+    }
+
+    /// Enable or disable tracking of the regions changed by drawing, used by
+    /// `sync_back`
+    pub fn set_dirty_tracking(&mut self, enabled: bool) {
+        self.pages.set_dirty_tracking(enabled);
+    }
+
+    /// Fetch the regions of the inactive page changed since it was last
+    /// brought up to date
+    pub fn dirty(&self) -> &DirtyRects {
+        self.pages.dirty()
+    }
+
+    /// Bring the inactive page up to date with the active page, by copying
+    /// the regions changed in the active page since it was last synced.
+    ///
+    /// With dirty tracking enabled, calling this after each `swap` keeps
+    /// both pages identical, so only changes need drawing each frame.
+    pub fn sync_back(&mut self) {
+        self.pages.sync_back();
+    }
 }
@@ -0,0 +1,126 @@
+//! Hardware colour blending and brightness fades
+
+use core::ptr;
+
+use crate::graphics::Layer;
+use crate::memory::{REG_BLDALPHA, REG_BLDCNT, REG_BLDY};
+
+/// Maximum blend / brightness coefficient (1.0)
+pub const MAX: u8 = 16;
+
+/// Colour special effect modes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BlendMode {
+    /// No special effects
+    Off = 0,
+    /// Alpha blend the first targets over the second targets
+    Alpha = 1,
+    /// Fade the first targets towards white
+    Brighten = 2,
+    /// Fade the first targets towards black
+    Darken = 3,
+}
+
+/// Colour special effects control
+///
+/// Semi-transparent objects are always blended as first targets, regardless
+/// of the mode and targets set here.
+#[derive(Debug, PartialEq)]
+pub struct Blend {
+    control: u16,
+    alpha: u16,
+    brightness: u16,
+}
+
+impl Blend {
+    /// Create a new blend controller, with special effects disabled
+    pub fn new() -> Blend {
+        let mut b = Blend {
+            control: 0,
+            alpha: 0,
+            brightness: 0,
+        };
+        b.write();
+        b
+    }
+
+    /// Write the shadow registers to hardware
+    fn write(&mut self) {
+        unsafe {
+            ptr::write_volatile(REG_BLDCNT as *mut u16, self.control);
+            ptr::write_volatile(REG_BLDALPHA as *mut u16, self.alpha);
+            ptr::write_volatile(REG_BLDY as *mut u16, self.brightness);
+        }
+    }
+
+    /// Set the special effect mode.
+    pub fn set_mode(&mut self, mode: BlendMode) {
+        self.control = (self.control & !0x00C0) | ((mode as u16) << 6);
+        self.write();
+    }
+
+    /// Fetch the special effect mode
+    pub fn mode(&self) -> BlendMode {
+        match (self.control >> 6) & 0x03 {
+            1 => BlendMode::Alpha,
+            2 => BlendMode::Brighten,
+            3 => BlendMode::Darken,
+            _ => BlendMode::Off,
+        }
+    }
+
+    /// Set the `first` (top) and `second` (bottom) target layers.
+    ///
+    /// Alpha blending applies where a first target pixel is directly above a
+    /// second target pixel, brightness effects apply to all first targets.
+    pub fn set_targets(&mut self, first: &[Layer], second: &[Layer]) {
+        self.control = (self.control & 0x00C0) | Layer::mask(first) | (Layer::mask(second) << 8);
+        self.write();
+    }
+
+    /// Set the alpha blending coefficients (0-16), where the result is
+    /// `first * eva / 16 + second * evb / 16`.
+    pub fn set_alpha(&mut self, eva: u8, evb: u8) {
+        let (eva, evb) = (eva.min(MAX) as u16, evb.min(MAX) as u16);
+        self.alpha = eva | (evb << 8);
+        self.write();
+    }
+
+    /// Fetch the alpha blending coefficients as `(eva, evb)`
+    pub fn alpha(&self) -> (u8, u8) {
+        ((self.alpha & 0x1F) as u8, (self.alpha >> 8) as u8)
+    }
+
+    /// Set the brightness coefficient (0-16) for the brighten and darken
+    /// modes, where 16 is fully white or black.
+    pub fn set_brightness(&mut self, evy: u8) {
+        self.brightness = evy.min(MAX) as u16;
+        self.write();
+    }
+
+    /// Fetch the brightness coefficient
+    ///
+    /// BLDY is write only, so this returns the last value set.
+    pub fn brightness(&self) -> u8 {
+        self.brightness as u8
+    }
+
+    /// Fade the whole screen towards black by `level` (0-16).
+    pub fn fade_to_black(&mut self, level: u8) {
+        self.set_targets(&Layer::ALL, &[]);
+        self.set_mode(BlendMode::Darken);
+        self.set_brightness(level);
+    }
+
+    /// Fade the whole screen towards white by `level` (0-16).
+    pub fn fade_to_white(&mut self, level: u8) {
+        self.set_targets(&Layer::ALL, &[]);
+        self.set_mode(BlendMode::Brighten);
+        self.set_brightness(level);
+    }
+
+    /// Disable special effects.
+    pub fn disable(&mut self) {
+        self.set_mode(BlendMode::Off);
+    }
+}
@@ -1,14 +1,29 @@
 //! Colours
 
-/// Helper macro to build static colour definitions
+/// Helper macro to build static colour definitions from 5-bit channels
+#[macro_export]
 macro_rules! rgb16 {
     ($r:expr, $g:expr, $b:expr) => {
         (($r & 0x1F) as u16) | ((($g & 0x1F) as u16) << 5) | ((($b & 0x1F) as u16) << 10)
     };
 }
 
+/// GBA LCD gamma pre-correction, mapping a 5-bit channel intended for a
+/// standard (sRGB) display to one that appears similar on the GBA LCD
+const LCD_CORRECT: [u8; 32] = [
+    0, 5, 7, 9, 10, 11, 13, 14, 15, 16, 17, 18, 18, 19, 20, 21, 22, 22, 23, 24, 24, 25, 26, 26, 27,
+    28, 28, 29, 29, 30, 30, 31,
+];
+
+/// Inverse of `LCD_CORRECT`, mapping a 5-bit channel as shown on the GBA
+/// LCD to its appearance on a standard (sRGB) display
+const LCD_APPEARANCE: [u8; 32] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 5, 6, 6, 7, 8, 9, 10, 12, 13, 14, 15, 17, 18, 19, 21, 23, 24,
+    26, 27, 29, 31,
+];
+
 /// 16-bit colour (5 bits per channel)
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Colour(u16);
 
 impl Colour {
@@ -16,19 +31,173 @@ impl Colour {
     pub const MAX: u8 = 0x1F;
 
     /// Create a new colour from 16-bit representation
-    pub fn new(v: u16) -> Colour {
+    pub const fn new(v: u16) -> Colour {
         Colour(v)
     }
 
     /// Create a new colour from individual channels
-    pub fn rgb(red: u8, green: u8, blue: u8) -> Colour {
+    pub const fn rgb(red: u8, green: u8, blue: u8) -> Colour {
         Colour(rgb16!(red, green, blue))
     }
 
+    /// Create a new colour from individual 5-bit (0-31) channels. This is an
+    /// alias of `rgb`, naming the channel range explicitly.
+    pub const fn rgb5(red: u8, green: u8, blue: u8) -> Colour {
+        Colour::rgb(red, green, blue)
+    }
+
+    /// Create a new colour from individual 8-bit (0-255) channels, discarding
+    /// the low 3 bits of each
+    pub const fn rgb8(red: u8, green: u8, blue: u8) -> Colour {
+        Colour(rgb16!(red >> 3, green >> 3, blue >> 3))
+    }
+
+    /// Create a new colour from a packed `0xRRGGBB` value
+    pub const fn from_rgb888(v: u32) -> Colour {
+        Colour::rgb8((v >> 16) as u8, (v >> 8) as u8, v as u8)
+    }
+
+    /// Create a new colour from a hex string, as `#RRGGBB` or `RRGGBB`
+    pub fn from_hex(s: &str) -> Option<Colour> {
+        let s = s.as_bytes();
+        let s = match s.first() {
+            Some(b'#') => &s[1..],
+            _ => s,
+        };
+        if s.len() != 6 {
+            return None;
+        }
+        let mut v: u32 = 0;
+        for c in s {
+            let d = match *c {
+                b'0'..=b'9' => c - b'0',
+                b'a'..=b'f' => c - b'a' + 10,
+                b'A'..=b'F' => c - b'A' + 10,
+                _ => return None,
+            };
+            v = (v << 4) | d as u32;
+        }
+        Some(Colour::from_rgb888(v))
+    }
+
     /// Get this colour's 16-bit representation
-    pub fn u16(&self) -> u16 {
+    pub const fn u16(&self) -> u16 {
         self.0
     }
+
+    /// Get the 5-bit red channel
+    pub const fn red(&self) -> u8 {
+        (self.0 & 0x1F) as u8
+    }
+
+    /// Get the 5-bit green channel
+    pub const fn green(&self) -> u8 {
+        ((self.0 >> 5) & 0x1F) as u8
+    }
+
+    /// Get the 5-bit blue channel
+    pub const fn blue(&self) -> u8 {
+        ((self.0 >> 10) & 0x1F) as u8
+    }
+
+    /// Get this colour as a packed `0xRRGGBB` value, expanding each channel
+    /// to the full 8-bit range
+    pub const fn to_rgb888(&self) -> u32 {
+        let (r, g, b) = (self.red() as u32, self.green() as u32, self.blue() as u32);
+        ((r << 3 | r >> 2) << 16) | ((g << 3 | g >> 2) << 8) | (b << 3 | b >> 2)
+    }
+
+    /// Get this colour as an ASCII `#RRGGBB` hex string
+    pub fn to_hex(&self) -> [u8; 7] {
+        const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+        let v = self.to_rgb888();
+        let mut s = [b'#'; 7];
+        for i in 0..6 {
+            s[i + 1] = DIGITS[((v >> (20 - i * 4)) & 0x0F) as usize];
+        }
+        s
+    }
+
+    /// Linearly interpolate towards `other` by `t` (8.8 fixed point, where
+    /// `0x100` is `other`), per channel
+    pub fn lerp(&self, other: Colour, t: u16) -> Colour {
+        let t = t.min(0x100) as i32;
+        let mix = |a: u8, b: u8| (a as i32 + (((b as i32 - a as i32) * t) >> 8)) as u8;
+        Colour::rgb5(
+            mix(self.red(), other.red()),
+            mix(self.green(), other.green()),
+            mix(self.blue(), other.blue()),
+        )
+    }
+
+    /// Additive blend with `other`, saturating each channel
+    pub fn additive(&self, other: Colour) -> Colour {
+        Colour::rgb5(
+            (self.red() + other.red()).min(Colour::MAX),
+            (self.green() + other.green()).min(Colour::MAX),
+            (self.blue() + other.blue()).min(Colour::MAX),
+        )
+    }
+
+    /// Multiplicative blend with `other`, per channel
+    pub fn multiply(&self, other: Colour) -> Colour {
+        let mul = |a: u8, b: u8| (a as u16 * b as u16 / Colour::MAX as u16) as u8;
+        Colour::rgb5(
+            mul(self.red(), other.red()),
+            mul(self.green(), other.green()),
+            mul(self.blue(), other.blue()),
+        )
+    }
+
+    /// Convert to grayscale, using the luminance of the colour
+    pub fn grayscale(&self) -> Colour {
+        let (r, g, b) = (self.red() as u16, self.green() as u16, self.blue() as u16);
+        let y = ((r * 77 + g * 151 + b * 28) >> 8) as u8;
+        Colour::rgb5(y, y, y)
+    }
+
+    /// Convert to a sepia tone
+    pub fn sepia(&self) -> Colour {
+        let (r, g, b) = (self.red() as u32, self.green() as u32, self.blue() as u32);
+        let tone = |rm: u32, gm: u32, bm: u32| ((r * rm + g * gm + b * bm) >> 10).min(0x1F) as u8;
+        Colour::rgb5(
+            tone(402, 787, 194),
+            tone(357, 702, 172),
+            tone(279, 547, 134),
+        )
+    }
+
+    /// Apply GBA LCD gamma correction, so that a colour chosen on a standard
+    /// display appears similar on the (darker) GBA screen
+    pub fn lcd_correct(&self) -> Colour {
+        Colour::rgb5(
+            LCD_CORRECT[self.red() as usize],
+            LCD_CORRECT[self.green() as usize],
+            LCD_CORRECT[self.blue() as usize],
+        )
+    }
+
+    /// Approximate how this colour appears on the GBA LCD, as a colour for a
+    /// standard display (the inverse of `lcd_correct`)
+    pub fn lcd_appearance(&self) -> Colour {
+        Colour::rgb5(
+            LCD_APPEARANCE[self.red() as usize],
+            LCD_APPEARANCE[self.green() as usize],
+            LCD_APPEARANCE[self.blue() as usize],
+        )
+    }
+}
+
+impl From<u16> for Colour {
+    fn from(v: u16) -> Colour {
+        Colour::new(v)
+    }
+}
+
+impl From<Colour> for u16 {
+    fn from(c: Colour) -> u16 {
+        c.0
+    }
 }
 
 /// Helper colour: Red
@@ -54,3 +223,55 @@ pub const W: Colour = Colour(rgb16!(Colour::MAX, Colour::MAX, Colour::MAX));
 
 /// Helper colour: Black
 pub const BL: Colour = Colour(rgb16!(0, 0, 0));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgb888_round_trip() {
+        assert_eq!(W.to_rgb888(), 0xFFFFFF);
+        assert_eq!(BL.to_rgb888(), 0x000000);
+        assert_eq!(Colour::rgb8(255, 255, 255), W);
+        assert_eq!(Colour::rgb8(7, 8, 0xF8), Colour::rgb(0, 1, Colour::MAX));
+        for v in 0..=Colour::MAX {
+            let c = Colour::rgb(v, Colour::MAX - v, v / 2);
+            assert_eq!(Colour::from_rgb888(c.to_rgb888()), c);
+        }
+    }
+
+    #[test]
+    fn hex_strings() {
+        let orange = Colour::rgb(Colour::MAX, 16, 0);
+        assert_eq!(Colour::from_hex("#FF8000"), Some(orange));
+        assert_eq!(Colour::from_hex("ff8000"), Some(orange));
+        assert_eq!(&orange.to_hex(), b"#FF8400");
+        assert_eq!(&BL.to_hex(), b"#000000");
+
+        assert_eq!(Colour::from_hex(""), None);
+        assert_eq!(Colour::from_hex("#"), None);
+        assert_eq!(Colour::from_hex("#FF800"), None);
+        assert_eq!(Colour::from_hex("#FF80000"), None);
+        assert_eq!(Colour::from_hex("##FF800"), None);
+        assert_eq!(Colour::from_hex("#GG8000"), None);
+    }
+
+    #[test]
+    fn lerp_endpoints() {
+        assert_eq!(R.lerp(B, 0), R);
+        assert_eq!(R.lerp(B, 0x100), B);
+        assert_eq!(R.lerp(B, 0xFFFF), B);
+        assert_eq!(BL.lerp(W, 0x80), Colour::rgb(15, 15, 15));
+        assert_eq!(W.lerp(BL, 0x80), Colour::rgb(15, 15, 15));
+    }
+
+    #[test]
+    fn blends_saturate() {
+        assert_eq!(W.additive(W), W);
+        assert_eq!(W.multiply(W), W);
+        assert_eq!(W.multiply(BL), BL);
+        assert_eq!(W.grayscale(), W);
+        assert_eq!(W.lcd_correct(), W);
+        assert_eq!(BL.lcd_appearance(), BL);
+    }
+}
@@ -0,0 +1,154 @@
+//! Dirty rectangle tracking
+//!
+//! Records the regions of a frame buffer changed by drawing, so that page
+//! flipping modes can bring the back page up to date by copying only the
+//! changed regions from the front page, rather than redrawing every frame.
+//! A fixed number of rectangles is kept, with nearby rectangles merged as
+//! they are added.
+
+/// Maximum number of rectangles tracked before merging
+pub const MAX_RECTS: usize = 16;
+
+/// A rectangle of pixels
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    /// Create a new `width` by `height` rectangle at `(x, y)`
+    pub const fn new(x: usize, y: usize, width: usize, height: usize) -> Rect {
+        Rect {
+            x: x,
+            y: y,
+            width: width,
+            height: height,
+        }
+    }
+
+    /// Fetch the number of pixels in the rectangle
+    pub fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Compute the smallest rectangle containing both `self` and `other`
+    pub fn union(&self, other: &Rect) -> Rect {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Rect::new(x, y, right - x, bottom - y)
+    }
+
+    /// Check whether `self` and `other` overlap or share an edge
+    pub fn touches(&self, other: &Rect) -> bool {
+        self.x <= other.x + other.width
+            && other.x <= self.x + self.width
+            && self.y <= other.y + other.height
+            && other.y <= self.y + self.height
+    }
+}
+
+/// Set of changed rectangles
+#[derive(Debug, PartialEq)]
+pub struct DirtyRects {
+    rects: [Rect; MAX_RECTS],
+    len: usize,
+}
+
+impl DirtyRects {
+    /// Create a new empty set
+    pub fn new() -> DirtyRects {
+        DirtyRects {
+            rects: [Rect::new(0, 0, 0, 0); MAX_RECTS],
+            len: 0,
+        }
+    }
+
+    /// Record the rectangle `r` as changed.
+    ///
+    /// Rectangles touching an existing rectangle are merged with it. When
+    /// the set is full, `r` is merged with the rectangle it enlarges least.
+    pub fn add(&mut self, r: Rect) {
+        if r.area() == 0 {
+            return;
+        }
+        if let Some(e) = self.rects[..self.len].iter_mut().find(|e| e.touches(&r)) {
+            *e = e.union(&r);
+            return;
+        }
+        if self.len < MAX_RECTS {
+            self.rects[self.len] = r;
+            self.len += 1;
+            return;
+        }
+        let growth = |e: &Rect| e.union(&r).area() - e.area();
+        if let Some(e) = self.rects.iter_mut().min_by_key(|e| growth(e)) {
+            *e = e.union(&r);
+        }
+    }
+
+    /// Fetch the changed rectangles
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects[..self.len]
+    }
+
+    /// Check whether nothing has changed
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Forget all changed rectangles
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_rects_are_ignored() {
+        let mut d = DirtyRects::new();
+        d.add(Rect::new(10, 10, 0, 5));
+        d.add(Rect::new(10, 10, 5, 0));
+        assert!(d.is_empty());
+    }
+
+    #[test]
+    fn touching_rects_merge() {
+        let mut d = DirtyRects::new();
+        d.add(Rect::new(0, 0, 8, 8));
+        d.add(Rect::new(8, 0, 8, 8));
+        assert_eq!(d.rects(), &[Rect::new(0, 0, 16, 8)]);
+
+        // One pixel gap, kept separate
+        d.add(Rect::new(0, 9, 8, 8));
+        assert_eq!(d.rects().len(), 2);
+    }
+
+    #[test]
+    fn full_set_merges_with_least_growth() {
+        let mut d = DirtyRects::new();
+        for i in 0..MAX_RECTS {
+            d.add(Rect::new(i * 10, 0, 2, 2));
+        }
+        assert_eq!(d.rects().len(), MAX_RECTS);
+
+        d.add(Rect::new(53, 0, 2, 2));
+        assert_eq!(d.rects().len(), MAX_RECTS);
+        assert_eq!(d.rects()[5], Rect::new(50, 0, 5, 2));
+    }
+
+    #[test]
+    fn clear_empties_the_set() {
+        let mut d = DirtyRects::new();
+        d.add(Rect::new(0, 0, 1, 1));
+        d.clear();
+        assert!(d.is_empty());
+        assert_eq!(d.rects(), &[]);
+    }
+}
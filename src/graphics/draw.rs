@@ -0,0 +1,472 @@
+//! 2D drawing primitives
+//!
+//! Drawing routines over any `BitmapMode`, operating on the same buffer as
+//! `BitmapMode::set`. Coordinates are signed and shapes are clipped to the
+//! bounds of the mode, so they may lie partially (or entirely) off screen.
+//! Filled shapes are drawn as horizontal spans using `BitmapMode::hline`.
+
+use crate::graphics::bitmap::BitmapMode;
+
+/// Maximum number of edge crossings per row when filling a polygon
+pub const MAX_CROSSINGS: usize = 32;
+
+/// Maximum number of pending spans when flood filling
+pub const FILL_STACK: usize = 256;
+
+/// Fetch the bounds of `g` as signed `(width, height)`
+fn size<T, M: BitmapMode<T>>(g: &M) -> (i32, i32) {
+    let (w, h, _) = g.bounds();
+    (w as i32, h as i32)
+}
+
+/// Set the pixel at `(x, y)` to the colour `c`, if it is on screen
+pub fn pixel<T, M: BitmapMode<T>>(g: &mut M, x: i32, y: i32, c: T) {
+    let (w, h) = size(g);
+    if x >= 0 && x < w && y >= 0 && y < h {
+        g.set(x as usize, y as usize, c);
+    }
+}
+
+/// Draw a horizontal line from `(x0, y)` to `(x1, y)` inclusive
+pub fn hline<T: Copy, M: BitmapMode<T>>(g: &mut M, x0: i32, x1: i32, y: i32, c: T) {
+    let (w, h) = size(g);
+    let (x0, x1) = (x0.min(x1).max(0), x0.max(x1).min(w - 1));
+    if y >= 0 && y < h && x0 <= x1 {
+        g.hline(x0 as usize, x1 as usize, y as usize, c);
+    }
+}
+
+/// Draw a vertical line from `(x, y0)` to `(x, y1)` inclusive
+pub fn vline<T: Copy, M: BitmapMode<T>>(g: &mut M, x: i32, y0: i32, y1: i32, c: T) {
+    let (w, h) = size(g);
+    if x < 0 || x >= w {
+        return;
+    }
+    for y in y0.min(y1).max(0)..=y0.max(y1).min(h - 1) {
+        g.set(x as usize, y as usize, c);
+    }
+}
+
+/// Cohen-Sutherland region code of `(x, y)` against a `w` by `h` screen
+fn outcode(x: i64, y: i64, w: i64, h: i64) -> u8 {
+    let mut code = 0;
+    if x < 0 {
+        code |= 1;
+    } else if x >= w {
+        code |= 2;
+    }
+    if y < 0 {
+        code |= 4;
+    } else if y >= h {
+        code |= 8;
+    }
+    code
+}
+
+/// Clip the line from `a` to `b` to a `w` by `h` screen, returning `None` if
+/// it lies entirely off screen
+fn clip_line(a: (i32, i32), b: (i32, i32), w: i32, h: i32) -> Option<(i32, i32, i32, i32)> {
+    let (w, h) = (w as i64, h as i64);
+    let (mut x0, mut y0, mut x1, mut y1) = (a.0 as i64, a.1 as i64, b.0 as i64, b.1 as i64);
+    let mut c0 = outcode(x0, y0, w, h);
+    let mut c1 = outcode(x1, y1, w, h);
+    loop {
+        if c0 | c1 == 0 {
+            return Some((x0 as i32, y0 as i32, x1 as i32, y1 as i32));
+        }
+        if c0 & c1 != 0 {
+            return None;
+        }
+        let code = if c0 != 0 { c0 } else { c1 };
+        let (x, y) = if code & 8 != 0 {
+            (x0 + (x1 - x0) * (h - 1 - y0) / (y1 - y0), h - 1)
+        } else if code & 4 != 0 {
+            (x0 + (x1 - x0) * -y0 / (y1 - y0), 0)
+        } else if code & 2 != 0 {
+            (w - 1, y0 + (y1 - y0) * (w - 1 - x0) / (x1 - x0))
+        } else {
+            (0, y0 + (y1 - y0) * -x0 / (x1 - x0))
+        };
+        if code == c0 {
+            x0 = x;
+            y0 = y;
+            c0 = outcode(x0, y0, w, h);
+        } else {
+            x1 = x;
+            y1 = y;
+            c1 = outcode(x1, y1, w, h);
+        }
+    }
+}
+
+/// Draw a line from `(x0, y0)` to `(x1, y1)` inclusive
+pub fn line<T: Copy, M: BitmapMode<T>>(g: &mut M, x0: i32, y0: i32, x1: i32, y1: i32, c: T) {
+    let (w, h) = size(g);
+    let (x0, y0, x1, y1) = match clip_line((x0, y0), (x1, y1), w, h) {
+        Some(l) => l,
+        None => return,
+    };
+    if y0 == y1 {
+        return hline(g, x0, x1, y0, c);
+    }
+
+    // Bresenham's line algorithm, for all octants
+    let (dx, sx) = ((x1 - x0).abs(), if x0 < x1 { 1 } else { -1 });
+    let (dy, sy) = (-(y1 - y0).abs(), if y0 < y1 { 1 } else { -1 });
+    let mut err = dx + dy;
+    let (mut x, mut y) = (x0, y0);
+    loop {
+        g.set(x as usize, y as usize, c);
+        if x == x1 && y == y1 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+/// Draw the outline of a `width` by `height` rectangle at `(x, y)`
+pub fn rect<T: Copy, M: BitmapMode<T>>(g: &mut M, x: i32, y: i32, width: i32, height: i32, c: T) {
+    if width <= 0 || height <= 0 {
+        return;
+    }
+    let (x1, y1) = (x + width - 1, y + height - 1);
+    hline(g, x, x1, y, c);
+    hline(g, x, x1, y1, c);
+    vline(g, x, y, y1, c);
+    vline(g, x1, y, y1, c);
+}
+
+/// Draw a filled `width` by `height` rectangle at `(x, y)`
+pub fn fill_rect<T: Copy, M: BitmapMode<T>>(
+    g: &mut M,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    c: T,
+) {
+    if width <= 0 || height <= 0 {
+        return;
+    }
+    let (_, h) = size(g);
+    for row in y.max(0)..(y + height).min(h) {
+        hline(g, x, x + width - 1, row, c);
+    }
+}
+
+/// Draw the outline of a circle of radius `r` centred on `(cx, cy)`
+pub fn circle<T: Copy, M: BitmapMode<T>>(g: &mut M, cx: i32, cy: i32, r: i32, c: T) {
+    let (mut x, mut y, mut err) = (r, 0, 1 - r);
+    while x >= y {
+        pixel(g, cx + x, cy + y, c);
+        pixel(g, cx + y, cy + x, c);
+        pixel(g, cx - y, cy + x, c);
+        pixel(g, cx - x, cy + y, c);
+        pixel(g, cx - x, cy - y, c);
+        pixel(g, cx - y, cy - x, c);
+        pixel(g, cx + y, cy - x, c);
+        pixel(g, cx + x, cy - y, c);
+        y += 1;
+        if err < 0 {
+            err += 2 * y + 1;
+        } else {
+            x -= 1;
+            err += 2 * (y - x) + 1;
+        }
+    }
+}
+
+/// Draw a filled circle of radius `r` centred on `(cx, cy)`
+pub fn fill_circle<T: Copy, M: BitmapMode<T>>(g: &mut M, cx: i32, cy: i32, r: i32, c: T) {
+    let (mut x, mut y, mut err) = (r, 0, 1 - r);
+    while x >= y {
+        hline(g, cx - x, cx + x, cy + y, c);
+        hline(g, cx - x, cx + x, cy - y, c);
+        hline(g, cx - y, cx + y, cy + x, c);
+        hline(g, cx - y, cx + y, cy - x, c);
+        y += 1;
+        if err < 0 {
+            err += 2 * y + 1;
+        } else {
+            x -= 1;
+            err += 2 * (y - x) + 1;
+        }
+    }
+}
+
+/// Walk the first quadrant of an ellipse with radii `rx` and `ry` using the
+/// midpoint algorithm, calling `f` with each `(x, y)` offset from the centre
+fn ellipse_quadrant<F: FnMut(i32, i32)>(rx: i32, ry: i32, mut f: F) {
+    let (rx2, ry2) = (rx as i64 * rx as i64, ry as i64 * ry as i64);
+    let (mut x, mut y) = (0, ry);
+    let (mut px, mut py) = (0, 2 * rx2 * y as i64);
+
+    // Region 1, where the slope is shallower than -1
+    let mut p = ry2 - rx2 * ry as i64 + rx2 / 4;
+    while px < py {
+        f(x, y);
+        x += 1;
+        px += 2 * ry2;
+        if p < 0 {
+            p += ry2 + px;
+        } else {
+            y -= 1;
+            py -= 2 * rx2;
+            p += ry2 + px - py;
+        }
+    }
+
+    // Region 2, where the slope is steeper than -1
+    let (xf, yf) = (x as i64, y as i64);
+    p = (ry2 * (2 * xf + 1) * (2 * xf + 1)) / 4 + rx2 * (yf - 1) * (yf - 1) - rx2 * ry2;
+    while y >= 0 {
+        f(x, y);
+        y -= 1;
+        py -= 2 * rx2;
+        if p > 0 {
+            p += rx2 - py;
+        } else {
+            x += 1;
+            px += 2 * ry2;
+            p += rx2 - py + px;
+        }
+    }
+}
+
+/// Draw the outline of an ellipse with radii `rx` and `ry` centred on
+/// `(cx, cy)`
+pub fn ellipse<T: Copy, M: BitmapMode<T>>(g: &mut M, cx: i32, cy: i32, rx: i32, ry: i32, c: T) {
+    if rx <= 0 || ry <= 0 {
+        return line(
+            g,
+            cx - rx.max(0),
+            cy - ry.max(0),
+            cx + rx.max(0),
+            cy + ry.max(0),
+            c,
+        );
+    }
+    ellipse_quadrant(rx, ry, |x, y| {
+        pixel(g, cx + x, cy + y, c);
+        pixel(g, cx - x, cy + y, c);
+        pixel(g, cx + x, cy - y, c);
+        pixel(g, cx - x, cy - y, c);
+    });
+}
+
+/// Draw a filled ellipse with radii `rx` and `ry` centred on `(cx, cy)`
+pub fn fill_ellipse<T: Copy, M: BitmapMode<T>>(
+    g: &mut M,
+    cx: i32,
+    cy: i32,
+    rx: i32,
+    ry: i32,
+    c: T,
+) {
+    if rx <= 0 || ry <= 0 {
+        return line(
+            g,
+            cx - rx.max(0),
+            cy - ry.max(0),
+            cx + rx.max(0),
+            cy + ry.max(0),
+            c,
+        );
+    }
+    ellipse_quadrant(rx, ry, |x, y| {
+        hline(g, cx - x, cx + x, cy + y, c);
+        hline(g, cx - x, cx + x, cy - y, c);
+    });
+}
+
+/// Draw the outline of the closed polygon with vertices `points`
+pub fn polygon<T: Copy, M: BitmapMode<T>>(g: &mut M, points: &[(i32, i32)], c: T) {
+    let mut prev = match points.last() {
+        Some(p) => *p,
+        None => return,
+    };
+    for p in points {
+        line(g, prev.0, prev.1, p.0, p.1, c);
+        prev = *p;
+    }
+}
+
+/// Draw the filled polygon with vertices `points`, using the even-odd rule.
+///
+/// The filled area includes the polygon outline. Rows crossing more than
+/// `MAX_CROSSINGS` edges are only partially filled.
+pub fn fill_polygon<T: Copy, M: BitmapMode<T>>(g: &mut M, points: &[(i32, i32)], c: T) {
+    if points.len() < 3 {
+        return polygon(g, points, c);
+    }
+    let (_, h) = size(g);
+    let top = points.iter().map(|p| p.1).min().unwrap_or(0).max(0);
+    let bottom = points.iter().map(|p| p.1).max().unwrap_or(0).min(h - 1);
+
+    for y in top..=bottom {
+        let mut crossings = [0i32; MAX_CROSSINGS];
+        let mut n = 0;
+        let mut prev = points[points.len() - 1];
+        for p in points {
+            let ((x0, y0), (x1, y1)) = (prev, *p);
+            if n < MAX_CROSSINGS && (y0 <= y) != (y1 <= y) {
+                let dx = (x1 - x0) as i64 * (y - y0) as i64 / (y1 - y0) as i64;
+                crossings[n] = x0 + dx as i32;
+                n += 1;
+            }
+            prev = *p;
+        }
+        crossings[..n].sort_unstable();
+        for span in crossings[..n].chunks(2) {
+            if span.len() == 2 {
+                hline(g, span[0], span[1], y, c);
+            }
+        }
+    }
+    polygon(g, points, c);
+}
+
+/// Draw the outline of the triangle with vertices `a`, `b` and `c`
+pub fn triangle<T: Copy, M: BitmapMode<T>>(
+    g: &mut M,
+    a: (i32, i32),
+    b: (i32, i32),
+    c: (i32, i32),
+    colour: T,
+) {
+    polygon(g, &[a, b, c], colour)
+}
+
+/// Draw the filled triangle with vertices `a`, `b` and `c`
+pub fn fill_triangle<T: Copy, M: BitmapMode<T>>(
+    g: &mut M,
+    a: (i32, i32),
+    b: (i32, i32),
+    c: (i32, i32),
+    colour: T,
+) {
+    fill_polygon(g, &[a, b, c], colour)
+}
+
+/// Flood fill the area of matching colour containing `(x, y)` with the
+/// colour `c`, returning `false` if the fill was incomplete.
+///
+/// Fills are performed a span at a time, with up to `FILL_STACK` pending
+/// spans. Areas complex enough to overflow this are left partially filled,
+/// and may be completed by filling again.
+pub fn flood_fill<T: Copy + PartialEq, M: BitmapMode<T>>(g: &mut M, x: i32, y: i32, c: T) -> bool {
+    let (w, h) = size(g);
+    if x < 0 || x >= w || y < 0 || y >= h {
+        return true;
+    }
+    let target = g.get(x as usize, y as usize);
+    if target == c {
+        return true;
+    }
+
+    let (w, h) = (w as usize, h as usize);
+    let mut stack = [(0u16, 0u16); FILL_STACK];
+    let mut len = 1;
+    let mut complete = true;
+    stack[0] = (x as u16, y as u16);
+
+    while len > 0 {
+        len -= 1;
+        let (sx, sy) = (stack[len].0 as usize, stack[len].1 as usize);
+        if g.get(sx, sy) != target {
+            continue;
+        }
+
+        // Extend the span left and right, then fill it
+        let (mut l, mut r) = (sx, sx);
+        while l > 0 && g.get(l - 1, sy) == target {
+            l -= 1;
+        }
+        while r + 1 < w && g.get(r + 1, sy) == target {
+            r += 1;
+        }
+        g.hline(l, r, sy, c);
+
+        // Queue one seed for each run of matching pixels above and below
+        for ny in [sy.wrapping_sub(1), sy + 1].iter().cloned() {
+            if ny >= h {
+                continue;
+            }
+            let mut nx = l;
+            while nx <= r {
+                if g.get(nx, ny) != target {
+                    nx += 1;
+                    continue;
+                }
+                match len < FILL_STACK {
+                    true => {
+                        stack[len] = (nx as u16, ny as u16);
+                        len += 1;
+                    }
+                    false => complete = false,
+                }
+                while nx <= r && g.get(nx, ny) == target {
+                    nx += 1;
+                }
+            }
+        }
+    }
+    complete
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: i32 = 240;
+    const H: i32 = 160;
+
+    #[test]
+    fn lines_on_screen_are_unchanged() {
+        assert_eq!(clip_line((10, 10), (20, 30), W, H), Some((10, 10, 20, 30)));
+        assert_eq!(
+            clip_line((0, 0), (W - 1, H - 1), W, H),
+            Some((0, 0, W - 1, H - 1))
+        );
+    }
+
+    #[test]
+    fn lines_are_clipped_at_each_edge() {
+        assert_eq!(clip_line((-10, 50), (10, 50), W, H), Some((0, 50, 10, 50)));
+        assert_eq!(
+            clip_line((230, 20), (250, 20), W, H),
+            Some((230, 20, W - 1, 20))
+        );
+        assert_eq!(clip_line((50, -10), (50, 10), W, H), Some((50, 0, 50, 10)));
+        assert_eq!(
+            clip_line((50, 150), (50, 170), W, H),
+            Some((50, 150, 50, H - 1))
+        );
+        assert_eq!(clip_line((-10, -10), (10, 10), W, H), Some((0, 0, 10, 10)));
+    }
+
+    #[test]
+    fn lines_off_screen_are_rejected() {
+        assert_eq!(clip_line((-5, 0), (-1, 100), W, H), None);
+        assert_eq!(clip_line((W, 0), (W + 50, 100), W, H), None);
+        assert_eq!(clip_line((0, -5), (100, -1), W, H), None);
+        assert_eq!(clip_line((0, H), (100, H + 50), W, H), None);
+
+        // Passes the top left corner without crossing the screen
+        assert_eq!(clip_line((-10, 5), (5, -10), W, H), None);
+    }
+
+    #[test]
+    fn large_coordinates_do_not_overflow() {
+        let (a, b) = ((i32::min_value() / 2, 80), (i32::max_value() / 2, 80));
+        assert_eq!(clip_line(a, b, W, H), Some((0, 80, W - 1, 80)));
+    }
+}
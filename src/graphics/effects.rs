@@ -0,0 +1,198 @@
+//! Pallet effects
+//!
+//! Time based effects over a shadowed `Pallet`: fading towards a colour,
+//! cross-fading between two pallets, and rotating ranges of colours. Each
+//! effect is advanced by calling `tick` once per frame, and interpolates
+//! colours per 5-bit channel so effects work in every graphics mode.
+
+use crate::graphics::colour::Colour;
+use crate::graphics::pallet::{Pallet, BANK_SIZE, COLOURS};
+
+/// Pallet entries affected by an effect
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Selection {
+    /// The whole pallet
+    All,
+    /// The 16 colour banks set in the mask (bit 0 is bank 0)
+    Banks(u16),
+    /// Pallet indices from `start` up to (but not including) `end`
+    Range(usize, usize),
+}
+
+impl Selection {
+    /// Check whether the pallet index `i` is selected
+    pub fn contains(&self, i: usize) -> bool {
+        match *self {
+            Selection::All => true,
+            Selection::Banks(mask) => mask & (1 << (i / BANK_SIZE)) != 0,
+            Selection::Range(start, end) => i >= start && i < end,
+        }
+    }
+}
+
+/// Interpolate between the 16-bit colours `a` and `b` at `n` of `d` steps
+fn lerp(a: u16, b: u16, n: u16, d: u16) -> u16 {
+    let mut v = 0;
+    for shift in [0, 5, 10].iter() {
+        let ca = ((a >> shift) & 0x1F) as i32;
+        let cb = ((b >> shift) & 0x1F) as i32;
+        let c = ca + (cb - ca) * n as i32 / d as i32;
+        v |= (c as u16 & 0x1F) << shift;
+    }
+    v
+}
+
+/// Fade of selected pallet entries towards a single colour
+pub struct Fade {
+    source: [u16; COLOURS],
+    target: u16,
+    selection: Selection,
+    frames: u16,
+    frame: u16,
+    reversed: bool,
+}
+
+impl Fade {
+    /// Create a fade of the `selection` of `pallet` from its current colours
+    /// to `target` over `frames` frames
+    pub fn new(pallet: &Pallet, selection: Selection, target: Colour, frames: u16) -> Fade {
+        Fade {
+            source: *pallet.colours(),
+            target: target.u16(),
+            selection: selection,
+            frames: frames.max(1),
+            frame: 0,
+            reversed: false,
+        }
+    }
+
+    /// Advance the fade by one frame and update `pallet`, returning `true`
+    /// while the fade is still running
+    pub fn tick(&mut self, pallet: &mut Pallet) -> bool {
+        if !self.is_done() {
+            match self.reversed {
+                false => self.frame += 1,
+                true => self.frame -= 1,
+            }
+        }
+        self.apply(pallet);
+        !self.is_done()
+    }
+
+    /// Write the colours for the current frame to `pallet`
+    fn apply(&self, pallet: &mut Pallet) {
+        let colours = pallet.colours_mut();
+        for i in 0..COLOURS {
+            if self.selection.contains(i) {
+                colours[i] = lerp(self.source[i], self.target, self.frame, self.frames);
+            }
+        }
+    }
+
+    /// Check whether the fade has completed
+    pub fn is_done(&self) -> bool {
+        match self.reversed {
+            false => self.frame >= self.frames,
+            true => self.frame == 0,
+        }
+    }
+
+    /// Reverse the direction of the fade, so that it returns towards the
+    /// original colours (or back towards the target)
+    pub fn reverse(&mut self) {
+        self.reversed = !self.reversed;
+    }
+}
+
+/// Cross-fade of selected pallet entries between two sets of colours
+pub struct CrossFade {
+    from: [u16; COLOURS],
+    to: [u16; COLOURS],
+    selection: Selection,
+    frames: u16,
+    frame: u16,
+}
+
+impl CrossFade {
+    /// Create a cross-fade of `pallet` from its current colours to `to`
+    /// (loaded at index `offset`) over `frames` frames
+    pub fn new(pallet: &Pallet, offset: usize, to: &[Colour], frames: u16) -> CrossFade {
+        let mut target = *pallet.colours();
+        for (t, c) in target.iter_mut().skip(offset).zip(to) {
+            *t = c.u16();
+        }
+        let end = (offset + to.len()).min(COLOURS);
+        CrossFade {
+            from: *pallet.colours(),
+            to: target,
+            selection: Selection::Range(offset, end),
+            frames: frames.max(1),
+            frame: 0,
+        }
+    }
+
+    /// Advance the cross-fade by one frame and update `pallet`, returning
+    /// `true` while the cross-fade is still running
+    pub fn tick(&mut self, pallet: &mut Pallet) -> bool {
+        if self.frame < self.frames {
+            self.frame += 1;
+        }
+        let colours = pallet.colours_mut();
+        for i in 0..COLOURS {
+            if self.selection.contains(i) {
+                colours[i] = lerp(self.from[i], self.to[i], self.frame, self.frames);
+            }
+        }
+        !self.is_done()
+    }
+
+    /// Check whether the cross-fade has completed
+    pub fn is_done(&self) -> bool {
+        self.frame >= self.frames
+    }
+}
+
+/// Colour cycling, rotating a range of pallet entries
+pub struct Cycle {
+    start: usize,
+    end: usize,
+    period: u16,
+    counter: u16,
+    reverse: bool,
+}
+
+impl Cycle {
+    /// Create a colour cycle of the pallet indices from `start` up to (but
+    /// not including) `end`, rotating by one entry every `period` frames
+    pub fn new(start: usize, end: usize, period: u16) -> Cycle {
+        Cycle {
+            start: start,
+            end: end.min(COLOURS),
+            period: period.max(1),
+            counter: 0,
+            reverse: false,
+        }
+    }
+
+    /// Rotate towards lower indices rather than higher
+    pub fn with_reverse(mut self, reverse: bool) -> Cycle {
+        self.reverse = reverse;
+        self
+    }
+
+    /// Advance the cycle by one frame, rotating the colours in `pallet` when
+    /// the period has elapsed
+    pub fn tick(&mut self, pallet: &mut Pallet) {
+        self.counter += 1;
+        if self.counter < self.period || self.start >= self.end {
+            return;
+        }
+        self.counter = 0;
+
+        let range = &mut pallet.colours_mut()[self.start..self.end];
+        match self.reverse {
+            false => range.rotate_right(1),
+            true => range.rotate_left(1),
+        }
+    }
+}
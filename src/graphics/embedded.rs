@@ -0,0 +1,115 @@
+//! `embedded-graphics` integration
+//!
+//! Implements `DrawTarget` for the bitmap modes, so fonts, primitives and
+//! images from the embedded-graphics ecosystem can be drawn to them. `Mode3`
+//! and `Mode5` draw `Rgb555` colours, and `Mode4` draws `PalletIndex`
+//! colours.
+//!
+//! This module requires the `embedded-graphics` feature.
+
+use core::convert::Infallible;
+
+use embedded_graphics::drawable::Pixel;
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::pixelcolor::raw::{RawData, RawU8};
+use embedded_graphics::pixelcolor::{PixelColor, Rgb555, RgbColor};
+use embedded_graphics::DrawTarget;
+
+use crate::graphics::bitmap::{BitmapMode, Mode3, Mode4, Mode5};
+use crate::graphics::colour::Colour;
+use crate::graphics::draw;
+
+/// Pallet index colour, for pallet lookup modes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PalletIndex(pub u8);
+
+impl PixelColor for PalletIndex {
+    type Raw = RawU8;
+}
+
+impl From<RawU8> for PalletIndex {
+    fn from(raw: RawU8) -> PalletIndex {
+        PalletIndex(raw.into_inner())
+    }
+}
+
+impl From<Rgb555> for Colour {
+    fn from(c: Rgb555) -> Colour {
+        Colour::rgb5(c.r(), c.g(), c.b())
+    }
+}
+
+impl From<Colour> for Rgb555 {
+    fn from(c: Colour) -> Rgb555 {
+        Rgb555::new(c.red(), c.green(), c.blue())
+    }
+}
+
+/// Set the pixel at `p` in `g`, if it is on screen
+fn pixel<T, M: BitmapMode<T>>(g: &mut M, p: Point, c: T) -> Result<(), Infallible> {
+    draw::pixel(g, p.x, p.y, c);
+    Ok(())
+}
+
+/// Fill the whole of `g` with the colour `c`
+fn fill<T: Copy, M: BitmapMode<T>>(g: &mut M, c: T) -> Result<(), Infallible> {
+    g.fill(c);
+    Ok(())
+}
+
+/// Fetch the size of `g`
+fn bounds<T, M: BitmapMode<T>>(g: &M) -> Size {
+    let (w, h, _) = g.bounds();
+    Size::new(w as u32, h as u32)
+}
+
+impl DrawTarget<Rgb555> for Mode3 {
+    type Error = Infallible;
+
+    fn draw_pixel(&mut self, item: Pixel<Rgb555>) -> Result<(), Infallible> {
+        let Pixel(p, c) = item;
+        pixel(self, p, Colour::from(c).u16())
+    }
+
+    fn size(&self) -> Size {
+        bounds(self)
+    }
+
+    fn clear(&mut self, color: Rgb555) -> Result<(), Infallible> {
+        fill(self, Colour::from(color).u16())
+    }
+}
+
+impl DrawTarget<PalletIndex> for Mode4 {
+    type Error = Infallible;
+
+    fn draw_pixel(&mut self, item: Pixel<PalletIndex>) -> Result<(), Infallible> {
+        let Pixel(p, c) = item;
+        pixel(self, p, c.0)
+    }
+
+    fn size(&self) -> Size {
+        bounds(self)
+    }
+
+    fn clear(&mut self, color: PalletIndex) -> Result<(), Infallible> {
+        fill(self, color.0)
+    }
+}
+
+impl DrawTarget<Rgb555> for Mode5 {
+    type Error = Infallible;
+
+    fn draw_pixel(&mut self, item: Pixel<Rgb555>) -> Result<(), Infallible> {
+        let Pixel(p, c) = item;
+        pixel(self, p, Colour::from(c).u16())
+    }
+
+    fn size(&self) -> Size {
+        bounds(self)
+    }
+
+    fn clear(&mut self, color: Rgb555) -> Result<(), Infallible> {
+        fill(self, Colour::from(color).u16())
+    }
+}
@@ -0,0 +1,182 @@
+//! Bitmap images
+//!
+//! An `Image` is a rectangle of pixel data (16-bit colours, or pallet
+//! indices for `Mode4`) which can be blitted onto any `BitmapMode`. Blits
+//! are clipped to the screen, and write whole rows at a time using
+//! `BitmapMode::write_row`.
+
+use crate::graphics::bitmap::BitmapMode;
+
+/// Maximum width of a flipped blit, the widest bitmap mode
+const MAX_ROW: usize = 240;
+
+/// Image pixel data, stored in row order
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Image<'a, T> {
+    width: usize,
+    height: usize,
+    data: &'a [T],
+}
+
+impl<'a, T> Image<'a, T> {
+    /// Create a new `width` by `height` image from `data`, which must hold
+    /// at least `width * height` pixels
+    pub const fn new(width: usize, height: usize, data: &'a [T]) -> Image<'a, T> {
+        Image {
+            width: width,
+            height: height,
+            data: data,
+        }
+    }
+
+    /// Fetch the width of the image
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Fetch the height of the image
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Fetch the pixels of the row `y`
+    pub fn row(&self, y: usize) -> &'a [T] {
+        &self.data[y * self.width..(y + 1) * self.width]
+    }
+}
+
+impl<'a, T: Copy> Image<'a, T> {
+    /// Fetch the pixel at `(x, y)`
+    pub fn get(&self, x: usize, y: usize) -> T {
+        self.data[x + y * self.width]
+    }
+
+    /// Draw the whole image to `g` with its top left corner at `(x, y)`
+    pub fn blit<M: BitmapMode<T>>(&self, g: &mut M, x: i32, y: i32)
+    where
+        T: PartialEq,
+    {
+        self.blit_with(g, x, y, &Blit::new())
+    }
+
+    /// Draw the image to `g` with its top left corner at `(x, y)`, using the
+    /// options `opts`
+    pub fn blit_with<M: BitmapMode<T>>(&self, g: &mut M, x: i32, y: i32, opts: &Blit<T>)
+    where
+        T: PartialEq,
+    {
+        // Clamp the source rectangle to the image
+        let (sx, sy, sw, sh) = opts.source.unwrap_or((0, 0, self.width, self.height));
+        let (sx, sy) = (sx.min(self.width), sy.min(self.height));
+        let (sw, sh) = (sw.min(self.width - sx), sh.min(self.height - sy));
+
+        // Clip the destination rectangle to the screen
+        let (w, h, _) = g.bounds();
+        let (x0, y0) = (x.max(0), y.max(0));
+        let x1 = (x + sw as i32).min(w as i32);
+        let y1 = (y + sh as i32).min(h as i32);
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+        let (cols, skip) = ((x1 - x0) as usize, (x0 - x) as usize);
+        if opts.hflip && cols > MAX_ROW {
+            return;
+        }
+
+        for dy in y0..y1 {
+            let r = (dy - y) as usize;
+            let r = if opts.vflip { sh - 1 - r } else { r };
+            let src = &self.row(sy + r)[sx..sx + sw];
+            if opts.hflip {
+                let mut buf = [src[0]; MAX_ROW];
+                for (i, c) in buf[..cols].iter_mut().enumerate() {
+                    *c = src[sw - 1 - skip - i];
+                }
+                write_runs(g, x0 as usize, dy as usize, &buf[..cols], opts.key);
+            } else {
+                let row = &src[skip..skip + cols];
+                write_runs(g, x0 as usize, dy as usize, row, opts.key);
+            }
+        }
+    }
+}
+
+/// Write `row` to `g` at `(x, y)`, skipping pixels matching `key`
+fn write_runs<T: Copy + PartialEq, M: BitmapMode<T>>(
+    g: &mut M,
+    x: usize,
+    y: usize,
+    row: &[T],
+    key: Option<T>,
+) {
+    let key = match key {
+        Some(k) => k,
+        None => return g.write_row(x, y, row),
+    };
+    let mut i = 0;
+    while i < row.len() {
+        if row[i] == key {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < row.len() && row[i] != key {
+            i += 1;
+        }
+        g.write_row(x + start, y, &row[start..i]);
+    }
+}
+
+/// Image blit options
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Blit<T> {
+    source: Option<(usize, usize, usize, usize)>,
+    key: Option<T>,
+    hflip: bool,
+    vflip: bool,
+}
+
+impl<T> Blit<T> {
+    /// Create blit options drawing the whole image, without transparency
+    pub fn new() -> Blit<T> {
+        Blit {
+            source: None,
+            key: None,
+            hflip: false,
+            vflip: false,
+        }
+    }
+
+    /// Draw only the `width` by `height` rectangle at `(x, y)` within the
+    /// image
+    pub fn with_source(self, x: usize, y: usize, width: usize, height: usize) -> Blit<T> {
+        Blit {
+            source: Some((x, y, width, height)),
+            ..self
+        }
+    }
+
+    /// Treat pixels of colour `key` as transparent
+    pub fn with_key(self, key: T) -> Blit<T> {
+        Blit {
+            key: Some(key),
+            ..self
+        }
+    }
+
+    /// Mirror the image horizontally
+    pub fn with_hflip(self, hflip: bool) -> Blit<T> {
+        Blit {
+            hflip: hflip,
+            ..self
+        }
+    }
+
+    /// Mirror the image vertically
+    pub fn with_vflip(self, vflip: bool) -> Blit<T> {
+        Blit {
+            vflip: vflip,
+            ..self
+        }
+    }
+}
@@ -1,11 +1,31 @@
 //! Graphics implementation
 
+pub mod affine;
 pub mod bitmap;
+pub mod blend;
 pub mod colour;
+pub mod dirty;
+pub mod draw;
+pub mod effects;
+#[cfg(feature = "embedded-graphics")]
+pub mod embedded;
 pub mod helpers;
+pub mod image;
+pub mod mode7;
+pub mod mosaic;
+pub mod objtiles;
+pub mod pallet;
+pub mod scanline;
+pub mod sprite;
 pub mod tilemap;
+pub mod window;
+
+use gba::io::display::{DisplayControlSetting, DisplayMode, DISPCNT};
 
 use self::bitmap::BitmapMode;
+use self::tilemap::TiledMode;
+use crate::bios;
+use crate::interrupt::{self, Interrupt};
 
 /// Graphics modes
 #[derive(Debug, PartialEq)]
@@ -19,6 +39,64 @@ pub enum Mode {
     MODE5,
 }
 
+/// Display layers, as used by the blending and window registers
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Layer {
+    BG0 = 1 << 0,
+    BG1 = 1 << 1,
+    BG2 = 1 << 2,
+    BG3 = 1 << 3,
+    OBJ = 1 << 4,
+    Backdrop = 1 << 5,
+}
+
+impl Layer {
+    /// All display layers
+    pub const ALL: [Layer; 6] = [
+        Layer::BG0,
+        Layer::BG1,
+        Layer::BG2,
+        Layer::BG3,
+        Layer::OBJ,
+        Layer::Backdrop,
+    ];
+
+    /// Combine `layers` into a register bit mask
+    pub fn mask(layers: &[Layer]) -> u16 {
+        layers.iter().fold(0, |m, l| m | *l as u16)
+    }
+}
+
+/// Fetch the display control setting for switching to the mode `mode`.
+///
+/// All backgrounds are hidden and frame 0 is selected, while the object and
+/// window settings are kept from DISPCNT, so objects and windows enabled
+/// before a mode switch remain enabled.
+pub(crate) fn display_control(mode: DisplayMode) -> DisplayControlSetting {
+    DISPCNT
+        .read()
+        .with_mode(mode)
+        .with_bg0(false)
+        .with_bg1(false)
+        .with_bg2(false)
+        .with_bg3(false)
+        .with_frame1(false)
+}
+
+/// Wait for the start of the next VBlank, halting the CPU until then.
+///
+/// This enables the VBlank interrupt if needed, but the master interrupt
+/// enable must already be set with `interrupt::enable_master`, otherwise
+/// this never returns. In particular it must not be called within
+/// `interrupt::free`. The number of frames waited for can be found with
+/// `interrupt::frame_count`.
+pub fn wait_for_vblank() {
+    if !interrupt::is_enabled(Interrupt::VBlank) {
+        interrupt::enable(Interrupt::VBlank);
+    }
+    bios::vblank_intr_wait();
+}
+
 /// Container type for graphics mode information
 pub struct Graphics<T> {
     mode: T,
@@ -35,6 +113,31 @@ impl<T> Graphics<T> {
         &mut self.mode
     }
 
+    /// Wait for the start of the next VBlank (see `wait_for_vblank`)
+    pub fn wait_for_vblank(&self) {
+        wait_for_vblank()
+    }
+
+    /// Fetch the number of frames displayed (see `interrupt::frame_count`)
+    pub fn frame_count(&self) -> u32 {
+        interrupt::frame_count()
+    }
+
+    /// Set graphics to mode 0
+    pub fn mode0(self) -> Graphics<tilemap::Mode0> {
+        Graphics::<tilemap::Mode0>::new()
+    }
+
+    /// Set graphics to mode 1
+    pub fn mode1(self) -> Graphics<tilemap::Mode1> {
+        Graphics::<tilemap::Mode1>::new()
+    }
+
+    /// Set graphics to mode 2
+    pub fn mode2(self) -> Graphics<tilemap::Mode2> {
+        Graphics::<tilemap::Mode2>::new()
+    }
+
     /// Set graphics to mode 3
     pub fn mode3(self) -> Graphics<bitmap::Mode3> {
         Graphics::<bitmap::Mode3>::new()
@@ -51,6 +154,33 @@ impl<T> Graphics<T> {
     }
 }
 
+impl Graphics<tilemap::Mode0> {
+    /// Create a new Mode0 graphics instance
+    pub fn new() -> Graphics<tilemap::Mode0> {
+        let mut mode = tilemap::Mode0::new();
+        mode.enable();
+        Graphics::base(mode)
+    }
+}
+
+impl Graphics<tilemap::Mode1> {
+    /// Create a new Mode1 graphics instance
+    pub fn new() -> Graphics<tilemap::Mode1> {
+        let mut mode = tilemap::Mode1::new();
+        mode.enable();
+        Graphics::base(mode)
+    }
+}
+
+impl Graphics<tilemap::Mode2> {
+    /// Create a new Mode2 graphics instance
+    pub fn new() -> Graphics<tilemap::Mode2> {
+        let mut mode = tilemap::Mode2::new();
+        mode.enable();
+        Graphics::base(mode)
+    }
+}
+
 impl Graphics<bitmap::Mode3> {
     /// Create a new Mode3 graphics instance
     pub fn new() -> Graphics<bitmap::Mode3> {
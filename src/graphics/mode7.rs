@@ -0,0 +1,219 @@
+//! Mode 7 style perspective floor
+//!
+//! Renders an affine background (BG2 in mode 1, or BG2 / BG3 in mode 2) as a
+//! ground plane seen from a camera, by computing a new affine matrix and
+//! reference point for every scanline and streaming them into the affine
+//! registers with a `ScanlineTable`.
+//!
+//! Positions and heights are in map pixels as 24.8 fixed point, angles use
+//! the `graphics::affine` convention (`0x10000` is a full turn).
+
+use crate::graphics::affine::{cos, sin};
+use crate::graphics::scanline::ScanlineTable;
+use crate::memory::{REG_BG2PA, REG_BG3PA};
+
+/// Half the screen width, the horizontal centre of projection
+const HALF_WIDTH: i64 = 120;
+
+/// Affine registers (PA-PD, X and Y) for one scanline of an affine background
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AffineLine {
+    pub pa: i16,
+    pub pb: i16,
+    pub pc: i16,
+    pub pd: i16,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl AffineLine {
+    /// A scanline that samples far outside the map, which displays nothing
+    /// when wraparound is disabled
+    pub const fn empty() -> AffineLine {
+        AffineLine {
+            pa: 0,
+            pb: 0,
+            pc: 0,
+            pd: 0,
+            x: -(1 << 26),
+            y: -(1 << 26),
+        }
+    }
+}
+
+/// Create a scanline table driving the affine registers of BG2
+pub fn bg2_table() -> ScanlineTable<AffineLine> {
+    ScanlineTable::new(REG_BG2PA, AffineLine::empty())
+}
+
+/// Create a scanline table driving the affine registers of BG3
+pub fn bg3_table() -> ScanlineTable<AffineLine> {
+    ScanlineTable::new(REG_BG3PA, AffineLine::empty())
+}
+
+/// Camera above the floor plane
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    /// Map x position (24.8 fixed point)
+    pub x: i32,
+    /// Map y position (24.8 fixed point)
+    pub y: i32,
+    /// Height above the floor (24.8 fixed point)
+    pub height: i32,
+    /// Heading, where 0 faces towards the top of the map (-y) and angles
+    /// increase clockwise when viewed from above
+    pub yaw: u16,
+    /// Horizontal field of view
+    pub fov: u16,
+    /// Screen line of the horizon, lines at or above this are not drawn
+    pub horizon: i32,
+}
+
+impl Camera {
+    /// Create a new camera at `(x, y)` and `height` above the floor (24.8
+    /// fixed point), facing the top of the map with a 90 degree field of
+    /// view and the horizon at the top of the screen.
+    pub fn new(x: i32, y: i32, height: i32) -> Camera {
+        Camera {
+            x: x,
+            y: y,
+            height: height,
+            yaw: 0,
+            fov: 0x4000,
+            horizon: 0,
+        }
+    }
+
+    /// Distance from the eye to the projection plane, in screen pixels
+    /// (24.8 fixed point)
+    fn focal_length(&self) -> i64 {
+        let half = self.fov / 2;
+        let (s, c) = (sin(half) as i64, cos(half) as i64);
+        if s <= 0 {
+            return HALF_WIDTH << 8;
+        }
+        (HALF_WIDTH << 8) * c / s
+    }
+
+    /// Compute the affine registers for the screen line `line`
+    pub fn line(&self, line: usize) -> AffineLine {
+        let ys = line as i64 - self.horizon as i64;
+        if ys <= 0 || self.height <= 0 {
+            return AffineLine::empty();
+        }
+
+        let (s, c) = (sin(self.yaw) as i64, cos(self.yaw) as i64);
+        let d = self.focal_length();
+
+        // Map distance per screen pixel on this line (20.12 fixed point)
+        let lambda = ((self.height as i64) << 4) / ys;
+
+        // Offset from the camera to the left edge of the line, per unit lambda
+        // (forward is (sin, -cos), right is (cos, sin))
+        let fx = (d * s - (HALF_WIDTH << 8) * c) >> 12;
+        let fy = (-d * c - (HALF_WIDTH << 8) * s) >> 12;
+
+        AffineLine {
+            pa: clamp16((lambda * c) >> 16),
+            pb: 0,
+            pc: clamp16((lambda * s) >> 16),
+            pd: 0,
+            x: (self.x as i64 + ((lambda * fx) >> 12)) as i32,
+            y: (self.y as i64 + ((lambda * fy) >> 12)) as i32,
+        }
+    }
+
+    /// Fill `table` with the affine registers for every scanline
+    pub fn fill(&self, table: &mut ScanlineTable<AffineLine>) {
+        for (i, e) in table.entries().iter_mut().enumerate() {
+            *e = self.line(i);
+        }
+    }
+
+    /// Project the map position `(x, y)` (24.8 fixed point) onto the screen.
+    ///
+    /// Returns the screen position `(x, y)` in pixels and the scale of
+    /// objects at that distance (8.8 fixed point), or `None` if the position
+    /// is behind the camera.
+    pub fn project(&self, x: i32, y: i32) -> Option<(i32, i32, i32)> {
+        let (s, c) = (sin(self.yaw) as i64, cos(self.yaw) as i64);
+        let (vx, vy) = (x as i64 - self.x as i64, y as i64 - self.y as i64);
+
+        let depth = (vx * s - vy * c) >> 12;
+        let lateral = (vx * c + vy * s) >> 12;
+        if depth <= 0 {
+            return None;
+        }
+
+        let d = self.focal_length();
+        let sx = HALF_WIDTH + ((lateral * d / depth) >> 8);
+        let sy = self.horizon as i64 + ((self.height as i64 * d / depth) >> 8);
+        Some((sx as i32, sy as i32, ((d << 8) / depth) as i32))
+    }
+}
+
+/// Saturate a fixed point value into an affine parameter
+fn clamp16(v: i64) -> i16 {
+    if v > i16::max_value() as i64 {
+        i16::max_value()
+    } else if v < i16::min_value() as i64 {
+        i16::min_value()
+    } else {
+        v as i16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_at_or_above_the_horizon_are_empty() {
+        let mut camera = Camera::new(0, 0, 32 << 8);
+        camera.horizon = 40;
+        assert_eq!(camera.line(0), AffineLine::empty());
+        assert_eq!(camera.line(40), AffineLine::empty());
+        assert_ne!(camera.line(41), AffineLine::empty());
+
+        camera.height = 0;
+        assert_eq!(camera.line(100), AffineLine::empty());
+    }
+
+    #[test]
+    fn line_matches_projection() {
+        // 32 lines below the horizon at height 32, one map pixel per screen
+        // pixel and 120 map pixels ahead
+        let camera = Camera::new(0, 0, 32 << 8);
+        let l = camera.line(32);
+        assert_eq!((l.pa, l.pb, l.pc, l.pd), (0x100, 0, 0, 0));
+        assert_eq!((l.x, l.y), (-120 << 8, -120 << 8));
+        assert_eq!(camera.project(0, -120 << 8), Some((120, 32, 0x100)));
+    }
+
+    #[test]
+    fn project_follows_yaw() {
+        let mut camera = Camera::new(0, 0, 32 << 8);
+        camera.yaw = 0x4000;
+        assert_eq!(camera.project(120 << 8, 0), Some((120, 32, 0x100)));
+        assert_eq!(camera.project(0, -120 << 8), None);
+    }
+
+    #[test]
+    fn project_behind_camera_is_none() {
+        let camera = Camera::new(0, 0, 32 << 8);
+        assert_eq!(camera.project(0, 0), None);
+        assert_eq!(camera.project(0, 10 << 8), None);
+        assert_eq!(camera.project(100 << 8, 0), None);
+    }
+
+    #[test]
+    fn parameters_saturate() {
+        assert_eq!(clamp16(1 << 20), i16::max_value());
+        assert_eq!(clamp16(-(1 << 20)), i16::min_value());
+        assert_eq!(clamp16(-5), -5);
+
+        let camera = Camera::new(0, 0, 1 << 24);
+        assert_eq!(camera.line(1).pa, i16::max_value());
+    }
+}
@@ -0,0 +1,148 @@
+//! Mosaic effect
+//!
+//! The mosaic effect enlarges pixels into blocks for backgrounds and objects
+//! with mosaic enabled (see `BackgroundControl::with_mosaic` and
+//! `ObjectAttributes::set_mosaic`). Block sizes are in pixels, from 1 (no
+//! effect) to 16.
+
+use core::ptr;
+
+use crate::memory::REG_MOSAIC;
+
+/// Maximum mosaic block size
+pub const MAX: u8 = 16;
+
+/// Layers affected by a mosaic animation
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MosaicTarget {
+    Backgrounds,
+    Objects,
+    Both,
+}
+
+/// Mosaic size control
+#[derive(Debug, PartialEq)]
+pub struct Mosaic {
+    value: u16,
+}
+
+impl Mosaic {
+    /// Create a new mosaic controller, with all block sizes set to 1
+    pub fn new() -> Mosaic {
+        let mut m = Mosaic { value: 0 };
+        m.write();
+        m
+    }
+
+    /// Write the shadow register to hardware
+    fn write(&mut self) {
+        unsafe { ptr::write_volatile(REG_MOSAIC as *mut u16, self.value) }
+    }
+
+    /// Set the background block size to `(h, v)` pixels (1-16).
+    pub fn set_bg(&mut self, h: u8, v: u8) {
+        self.value = (self.value & 0xFF00) | encode(h, v);
+        self.write();
+    }
+
+    /// Fetch the background block size as `(h, v)`
+    pub fn bg(&self) -> (u8, u8) {
+        decode(self.value)
+    }
+
+    /// Set the object block size to `(h, v)` pixels (1-16).
+    pub fn set_obj(&mut self, h: u8, v: u8) {
+        self.value = (self.value & 0x00FF) | (encode(h, v) << 8);
+        self.write();
+    }
+
+    /// Fetch the object block size as `(h, v)`
+    pub fn obj(&self) -> (u8, u8) {
+        decode(self.value >> 8)
+    }
+
+    /// Set the block size of `target` to `(h, v)` pixels (1-16).
+    pub fn set(&mut self, target: MosaicTarget, h: u8, v: u8) {
+        match target {
+            MosaicTarget::Backgrounds => self.set_bg(h, v),
+            MosaicTarget::Objects => self.set_obj(h, v),
+            MosaicTarget::Both => {
+                self.set_bg(h, v);
+                self.set_obj(h, v);
+            }
+        }
+    }
+}
+
+/// Encode a block size into the low byte of a MOSAIC register value
+fn encode(h: u8, v: u8) -> u16 {
+    let h = h.max(1).min(MAX) as u16 - 1;
+    let v = v.max(1).min(MAX) as u16 - 1;
+    h | (v << 4)
+}
+
+/// Decode a block size from the low byte of a MOSAIC register value
+fn decode(value: u16) -> (u8, u8) {
+    ((value & 0x0F) as u8 + 1, ((value >> 4) & 0x0F) as u8 + 1)
+}
+
+/// Mosaic size animation, interpolating from one block size to another over
+/// a number of frames
+#[derive(Debug, PartialEq)]
+pub struct MosaicAnimation {
+    target: MosaicTarget,
+    from: (u8, u8),
+    to: (u8, u8),
+    frames: u16,
+    frame: u16,
+}
+
+impl MosaicAnimation {
+    /// Create a new animation of `target` from the block size `from` to
+    /// `to` (as `(h, v)`) over `frames` frames
+    pub fn new(target: MosaicTarget, from: (u8, u8), to: (u8, u8), frames: u16) -> MosaicAnimation {
+        MosaicAnimation {
+            target: target,
+            from: from,
+            to: to,
+            frames: frames.max(1),
+            frame: 0,
+        }
+    }
+
+    /// Advance the animation by one frame and update `mosaic`, returning
+    /// `true` while the animation is still running.
+    ///
+    /// This should be called once per frame.
+    pub fn tick(&mut self, mosaic: &mut Mosaic) -> bool {
+        if self.frame < self.frames {
+            self.frame += 1;
+        }
+        let h = lerp(self.from.0, self.to.0, self.frame, self.frames);
+        let v = lerp(self.from.1, self.to.1, self.frame, self.frames);
+        mosaic.set(self.target, h, v);
+        !self.is_done()
+    }
+
+    /// Check whether the animation has completed
+    pub fn is_done(&self) -> bool {
+        self.frame >= self.frames
+    }
+
+    /// Restart the animation
+    pub fn reset(&mut self) {
+        self.frame = 0;
+    }
+
+    /// Reverse the animation direction, continuing from the current size
+    pub fn reverse(&mut self) {
+        core::mem::swap(&mut self.from, &mut self.to);
+        self.frame = self.frames - self.frame;
+    }
+}
+
+/// Interpolate between `a` and `b` at `n` of `d` steps
+fn lerp(a: u8, b: u8, n: u16, d: u16) -> u8 {
+    let (a, b) = (a as i32, b as i32);
+    (a + (b - a) * n as i32 / d as i32) as u8
+}
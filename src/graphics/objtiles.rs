@@ -0,0 +1,401 @@
+//! Object (sprite) tile VRAM allocation
+//!
+//! Object tiles live in the last 32KB of VRAM, as 1024 slots of 32 bytes
+//! (one 4bpp tile, or half of an 8bpp tile). `ObjTiles` hands out ranges of
+//! slots sized for an object, so that independent game systems can load
+//! sprite graphics without overwriting each other.
+//!
+//! With 1D mapping an object's tiles are a contiguous run of slots, with 2D
+//! mapping they are a rectangle within a 32x32 slot matrix.
+
+use embedded_builder::region::Region;
+
+use crate::graphics::sprite::{ObjectColour, ObjectMapping, ObjectSize};
+use crate::graphics::tilemap::TileSingle;
+use crate::memory::{KB, VRAM};
+
+/// Number of 32-byte tile slots in object VRAM
+pub const OBJ_TILES: usize = 1024;
+
+/// First tile slot usable in the bitmap modes (3-5), where the lower half of
+/// object tile VRAM is used by the frame buffers
+pub const BITMAP_FIRST_TILE: usize = 512;
+
+/// Width of the tile matrix used by 2D mapping
+const MATRIX_WIDTH: usize = 32;
+
+/// Start of object tile VRAM
+const OBJ_VRAM: usize = VRAM.0 + 64 * KB;
+
+/// Object tile errors
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TileError {
+    /// The tiles do not fit within the usable object tile region
+    OutOfRange,
+}
+
+/// A range of allocated tile slots
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TileRange {
+    start: u16,
+    width: u16,
+    height: u16,
+    stride: u16,
+}
+
+impl TileRange {
+    /// Fetch the tile index of the first slot, as used by
+    /// `ObjectAttributes::set_tile`
+    pub fn index(&self) -> u16 {
+        self.start
+    }
+
+    /// Fetch the number of slots in the range
+    pub fn len(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// Check whether the range contains no slots
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Fetch the slot index of the `n`th slot in the range (in row order)
+    fn slot(&self, n: usize) -> usize {
+        let (w, s) = (self.width as usize, self.stride as usize);
+        self.start as usize + (n / w) * s + (n % w)
+    }
+}
+
+/// Object tile VRAM fragmentation statistics
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Fragmentation {
+    /// Number of free slots
+    pub free: usize,
+    /// Size of the largest contiguous run of free slots
+    pub largest: usize,
+    /// Number of separate runs of free slots
+    pub runs: usize,
+}
+
+/// Tile slot allocator
+#[derive(Debug, PartialEq)]
+pub struct TileAllocator {
+    used: [u32; OBJ_TILES / 32],
+    mapping: ObjectMapping,
+    first: usize,
+}
+
+impl TileAllocator {
+    /// Create a new allocator covering all of object tile VRAM
+    pub fn new(mapping: ObjectMapping) -> TileAllocator {
+        TileAllocator::with_first(mapping, 0)
+    }
+
+    /// Create a new allocator only covering slots from `first` onwards
+    pub fn with_first(mapping: ObjectMapping, first: usize) -> TileAllocator {
+        TileAllocator {
+            used: [0; OBJ_TILES / 32],
+            mapping: mapping,
+            first: first,
+        }
+    }
+
+    /// Fetch the first slot covered by the allocator
+    pub fn first(&self) -> usize {
+        self.first
+    }
+
+    /// Fetch the object tile mapping used by the allocator
+    pub fn mapping(&self) -> ObjectMapping {
+        self.mapping
+    }
+
+    /// Change the object tile mapping.
+    ///
+    /// Existing allocations are laid out differently under each mapping, so
+    /// this releases all slots.
+    pub fn set_mapping(&mut self, mapping: ObjectMapping) {
+        self.mapping = mapping;
+        self.reset();
+    }
+
+    fn is_free(&self, i: usize) -> bool {
+        i >= self.first && self.used[i / 32] & (1 << (i % 32)) == 0
+    }
+
+    fn mark(&mut self, r: &TileRange, used: bool) {
+        for n in 0..r.len() {
+            let i = r.slot(n);
+            match used {
+                true => self.used[i / 32] |= 1 << (i % 32),
+                false => self.used[i / 32] &= !(1 << (i % 32)),
+            }
+        }
+    }
+
+    /// Allocate the slots for an object of `size` and `colour` depth,
+    /// returning `None` if there is no space.
+    pub fn alloc(&mut self, size: ObjectSize, colour: ObjectColour) -> Option<TileRange> {
+        let (w, h) = size.tiles();
+        let units = match colour {
+            ObjectColour::Bpp4 => 1,
+            ObjectColour::Bpp8 => 2,
+        };
+        let r = match self.mapping {
+            ObjectMapping::OneD => self.find_run(w * h * units, units),
+            ObjectMapping::TwoD => self.find_rect(w * units, h, units),
+        }?;
+        self.mark(&r, true);
+        Some(r)
+    }
+
+    /// Allocate `count` contiguous slots (in index order) aligned to
+    /// `align`, returning `None` if there is no space.
+    ///
+    /// This is intended for 1D mapping, or for loading a single row of tiles
+    /// with 2D mapping.
+    pub fn alloc_slots(&mut self, count: usize, align: usize) -> Option<TileRange> {
+        let r = self.find_run(count, align)?;
+        self.mark(&r, true);
+        Some(r)
+    }
+
+    /// Release the slots in `r`.
+    pub fn free(&mut self, r: TileRange) {
+        self.mark(&r, false);
+    }
+
+    /// Release all slots.
+    pub fn reset(&mut self) {
+        self.used = [0; OBJ_TILES / 32];
+    }
+
+    /// Find `count` free slots in a contiguous run
+    fn find_run(&self, count: usize, align: usize) -> Option<TileRange> {
+        let align = align.max(1);
+        let mut start = align_up(self.first, align);
+        while count > 0 && start + count <= OBJ_TILES {
+            match (start..start + count).find(|i| !self.is_free(*i)) {
+                None => {
+                    return Some(TileRange {
+                        start: start as u16,
+                        width: count as u16,
+                        height: 1,
+                        stride: count as u16,
+                    })
+                }
+                Some(used) => start = align_up(used + 1, align),
+            }
+        }
+        None
+    }
+
+    /// Find a free rectangle of `width` by `height` slots in the 2D matrix
+    fn find_rect(&self, width: usize, height: usize, align: usize) -> Option<TileRange> {
+        if width == 0 || width > MATRIX_WIDTH || height == 0 {
+            return None;
+        }
+        let rows = OBJ_TILES / MATRIX_WIDTH;
+        for row in self.first / MATRIX_WIDTH..rows.saturating_sub(height - 1) {
+            let mut col = 0;
+            while col + width <= MATRIX_WIDTH {
+                let start = row * MATRIX_WIDTH + col;
+                let r = TileRange {
+                    start: start as u16,
+                    width: width as u16,
+                    height: height as u16,
+                    stride: MATRIX_WIDTH as u16,
+                };
+                if (0..r.len()).all(|n| self.is_free(r.slot(n))) {
+                    return Some(r);
+                }
+                col += align;
+            }
+        }
+        None
+    }
+
+    /// Compute fragmentation statistics for the covered slots.
+    ///
+    /// With 2D mapping, runs do not continue across matrix rows.
+    pub fn fragmentation(&self) -> Fragmentation {
+        let mut f = Fragmentation {
+            free: 0,
+            largest: 0,
+            runs: 0,
+        };
+        let mut run = 0;
+        for i in self.first..OBJ_TILES {
+            if self.mapping == ObjectMapping::TwoD && i % MATRIX_WIDTH == 0 {
+                run = 0;
+            }
+            if self.is_free(i) {
+                if run == 0 {
+                    f.runs += 1;
+                }
+                run += 1;
+                f.free += 1;
+                f.largest = f.largest.max(run);
+            } else {
+                run = 0;
+            }
+        }
+        f
+    }
+}
+
+/// Round `v` up to a multiple of `align`
+fn align_up(v: usize, align: usize) -> usize {
+    (v + align - 1) / align * align
+}
+
+/// Object tile VRAM, with allocation of tile slots
+#[derive(Debug, PartialEq)]
+pub struct ObjTiles {
+    allocator: TileAllocator,
+    vram: Region<u16>,
+}
+
+impl ObjTiles {
+    /// Create a new handle covering all of object tile VRAM
+    pub fn new(mapping: ObjectMapping) -> ObjTiles {
+        ObjTiles {
+            allocator: TileAllocator::new(mapping),
+            vram: Region::new(OBJ_VRAM, OBJ_TILES * 16),
+        }
+    }
+
+    /// Create a new handle covering only the object tiles usable alongside
+    /// the bitmap modes.
+    ///
+    /// The bitmap mode frame buffers overlap the lower half of object tile
+    /// VRAM, so only slots from `BITMAP_FIRST_TILE` (512) onwards may be
+    /// loaded. The bitmap modes' own handles use 1D mapping, which the modes
+    /// select in DISPCNT on `enable`.
+    pub fn bitmap(mapping: ObjectMapping) -> ObjTiles {
+        ObjTiles {
+            allocator: TileAllocator::with_first(mapping, BITMAP_FIRST_TILE),
+            vram: Region::new(OBJ_VRAM, OBJ_TILES * 16),
+        }
+    }
+
+    /// Fetch the slot allocator
+    pub fn allocator(&mut self) -> &mut TileAllocator {
+        &mut self.allocator
+    }
+
+    /// Allocate the slots for an object of `size` and `colour` depth
+    pub fn alloc(&mut self, size: ObjectSize, colour: ObjectColour) -> Option<TileRange> {
+        self.allocator.alloc(size, colour)
+    }
+
+    /// Release the slots in `r`
+    pub fn free(&mut self, r: TileRange) {
+        self.allocator.free(r)
+    }
+
+    /// Compute fragmentation statistics
+    pub fn fragmentation(&self) -> Fragmentation {
+        self.allocator.fragmentation()
+    }
+
+    /// Load `tiles` into the allocated range `r`, in row order.
+    ///
+    /// Each 8bpp tile occupies two consecutive entries of `tiles`.
+    pub fn load(&mut self, r: &TileRange, tiles: &[TileSingle]) -> Result<(), TileError> {
+        if tiles.len() > r.len() {
+            return Err(TileError::OutOfRange);
+        }
+        for (n, t) in tiles.iter().enumerate() {
+            self.write_slot(r.slot(n), t)?;
+        }
+        Ok(())
+    }
+
+    /// Load `tiles` into consecutive slots starting at `index`, without
+    /// allocation.
+    ///
+    /// Slots outside the region covered by this handle are rejected.
+    pub fn load_at(&mut self, index: usize, tiles: &[TileSingle]) -> Result<(), TileError> {
+        for (n, t) in tiles.iter().enumerate() {
+            self.write_slot(index + n, t)?;
+        }
+        Ok(())
+    }
+
+    /// Write a single tile slot, packing bytes into halfwords as VRAM does
+    /// not support byte writes
+    fn write_slot(&mut self, i: usize, t: &TileSingle) -> Result<(), TileError> {
+        if i < self.allocator.first || i >= OBJ_TILES {
+            return Err(TileError::OutOfRange);
+        }
+        let base = i * t.len() / 2;
+        for j in 0..t.len() / 2 {
+            self.vram
+                .write_index(base + j, t[j * 2] as u16 | (t[j * 2 + 1] as u16) << 8);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_allocator_returns_none() {
+        let mut a = TileAllocator::new(ObjectMapping::OneD);
+        let mut last = None;
+        for i in 0..OBJ_TILES / 64 {
+            let r = a.alloc(ObjectSize::S64x64, ObjectColour::Bpp4).unwrap();
+            assert_eq!(r.index() as usize, i * 64);
+            last = Some(r);
+        }
+        assert_eq!(a.alloc(ObjectSize::S8x8, ObjectColour::Bpp4), None);
+        assert_eq!(a.fragmentation().free, 0);
+
+        // Freeing the last range makes exactly that space available again
+        a.free(last.unwrap());
+        let r = a.alloc(ObjectSize::S64x64, ObjectColour::Bpp4).unwrap();
+        assert_eq!(r.index() as usize, OBJ_TILES - 64);
+    }
+
+    #[test]
+    fn bpp8_is_aligned_to_two_slots() {
+        let mut a = TileAllocator::new(ObjectMapping::OneD);
+        a.alloc(ObjectSize::S8x8, ObjectColour::Bpp4).unwrap();
+        let r = a.alloc(ObjectSize::S8x8, ObjectColour::Bpp8).unwrap();
+        assert_eq!((r.index(), r.len()), (2, 2));
+        let f = a.fragmentation();
+        assert_eq!((f.free, f.runs), (OBJ_TILES - 3, 2));
+    }
+
+    #[test]
+    fn bitmap_allocator_starts_at_first() {
+        let mut a = TileAllocator::with_first(ObjectMapping::OneD, BITMAP_FIRST_TILE);
+        let r = a.alloc(ObjectSize::S8x8, ObjectColour::Bpp4).unwrap();
+        assert_eq!(r.index() as usize, BITMAP_FIRST_TILE);
+        for _ in 0..(OBJ_TILES - BITMAP_FIRST_TILE) / 64 - 1 {
+            a.alloc(ObjectSize::S64x64, ObjectColour::Bpp4).unwrap();
+        }
+        assert_eq!(a.alloc(ObjectSize::S64x64, ObjectColour::Bpp4), None);
+        assert!(a.alloc(ObjectSize::S32x32, ObjectColour::Bpp4).is_some());
+    }
+
+    #[test]
+    fn two_d_allocates_rectangles() {
+        let mut a = TileAllocator::new(ObjectMapping::TwoD);
+        let r = a.alloc(ObjectSize::S16x16, ObjectColour::Bpp4).unwrap();
+        assert_eq!((r.index(), r.len()), (0, 4));
+        let r = a.alloc(ObjectSize::S16x16, ObjectColour::Bpp4).unwrap();
+        assert_eq!(r.index(), 2);
+
+        // A 64x64 8bpp object is half a matrix row wide and 8 rows high
+        let mut a = TileAllocator::new(ObjectMapping::TwoD);
+        for _ in 0..8 {
+            a.alloc(ObjectSize::S64x64, ObjectColour::Bpp8).unwrap();
+        }
+        assert_eq!(a.alloc(ObjectSize::S8x8, ObjectColour::Bpp4), None);
+    }
+}
@@ -0,0 +1,158 @@
+//! Pallet management
+//!
+//! Background and object pallet RAM each hold 256 colours, used either as a
+//! single 256 colour pallet (8bpp graphics) or as 16 banks of 16 colours
+//! (4bpp graphics). A `Pallet` keeps a shadow copy in RAM which is edited
+//! freely and copied to pallet RAM with `commit` during VBlank.
+
+use crate::dma;
+use crate::graphics::colour::Colour;
+use crate::memory::PALRAM;
+
+/// Number of colours in a pallet
+pub const COLOURS: usize = 256;
+
+/// Number of 16 colour banks in a pallet
+pub const BANKS: usize = 16;
+
+/// Number of colours in a bank
+pub const BANK_SIZE: usize = 16;
+
+/// Pallet RAM regions
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PalletType {
+    /// Background pallet, colour 0 is the backdrop colour
+    Background,
+    /// Object (sprite) pallet
+    Object,
+}
+
+/// Shadowed pallet
+pub struct Pallet {
+    shadow: [u16; COLOURS],
+    base: usize,
+    banks_used: u16,
+    dirty: bool,
+}
+
+impl Pallet {
+    /// Create a new pallet for the pallet RAM region `t`, with all colours
+    /// set to black
+    pub fn new(t: PalletType) -> Pallet {
+        let base = match t {
+            PalletType::Background => PALRAM.0,
+            PalletType::Object => PALRAM.0 + 0x0200,
+        };
+        Pallet {
+            shadow: [0; COLOURS],
+            base: base,
+            banks_used: 0,
+            dirty: true,
+        }
+    }
+
+    /// Create a new background pallet
+    pub fn background() -> Pallet {
+        Pallet::new(PalletType::Background)
+    }
+
+    /// Create a new object pallet
+    pub fn object() -> Pallet {
+        Pallet::new(PalletType::Object)
+    }
+
+    /// Set the pallet index `i` to the colour `c`
+    pub fn set(&mut self, i: usize, c: Colour) {
+        self.shadow[i] = c.u16();
+        self.dirty = true;
+    }
+
+    /// Get the colour at the pallet index `i`
+    pub fn get(&self, i: usize) -> Colour {
+        Colour::new(self.shadow[i])
+    }
+
+    /// Load `colours` into the pallet starting at index `offset`.
+    ///
+    /// Colours beyond the end of the pallet are ignored.
+    pub fn load(&mut self, offset: usize, colours: &[Colour]) {
+        for (s, c) in self.shadow.iter_mut().skip(offset).zip(colours) {
+            *s = c.u16();
+        }
+        self.dirty = true;
+    }
+
+    /// Set the colour `i` (0-15) of the 16 colour bank `bank` to `c`
+    pub fn set_bank(&mut self, bank: usize, i: usize, c: Colour) {
+        assert!(bank < BANKS, "pallet bank out of range");
+        assert!(i < BANK_SIZE, "pallet bank index out of range");
+        self.set(bank * BANK_SIZE + i, c)
+    }
+
+    /// Load up to 16 `colours` into the 16 colour bank `bank`
+    pub fn load_bank(&mut self, bank: usize, colours: &[Colour]) {
+        assert!(bank < BANKS, "pallet bank out of range");
+        let n = colours.len().min(BANK_SIZE);
+        self.load(bank * BANK_SIZE, &colours[..n])
+    }
+
+    /// Allocate an unused 16 colour bank, returning `None` if all banks are
+    /// in use.
+    pub fn alloc_bank(&mut self) -> Option<usize> {
+        for b in 0..BANKS {
+            if self.banks_used & (1 << b) == 0 {
+                self.banks_used |= 1 << b;
+                return Some(b);
+            }
+        }
+        None
+    }
+
+    /// Allocate an unused 16 colour bank and load `colours` into it
+    pub fn alloc_bank_with(&mut self, colours: &[Colour]) -> Option<usize> {
+        let b = self.alloc_bank()?;
+        self.load_bank(b, colours);
+        Some(b)
+    }
+
+    /// Mark the 16 colour bank `bank` as in use, for banks loaded without
+    /// allocation
+    pub fn reserve_bank(&mut self, bank: usize) {
+        assert!(bank < BANKS, "pallet bank out of range");
+        self.banks_used |= 1 << bank;
+    }
+
+    /// Release the 16 colour bank `bank`
+    pub fn free_bank(&mut self, bank: usize) {
+        assert!(bank < BANKS, "pallet bank out of range");
+        self.banks_used &= !(1 << bank);
+    }
+
+    /// Fetch the raw 16-bit colours of the shadow pallet
+    pub fn colours(&self) -> &[u16; COLOURS] {
+        &self.shadow
+    }
+
+    /// Fetch the raw 16-bit colours of the shadow pallet for editing
+    pub fn colours_mut(&mut self) -> &mut [u16; COLOURS] {
+        self.dirty = true;
+        &mut self.shadow
+    }
+
+    /// Check whether the shadow pallet has changed since the last commit
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Copy the shadow pallet into pallet RAM, if it has changed.
+    ///
+    /// Pallet RAM should only be written during VBlank, to avoid visible
+    /// artifacts.
+    pub fn commit(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        unsafe { dma::copy16(self.shadow.as_ptr(), self.base as *mut u16, COLOURS) };
+        self.dirty = false;
+    }
+}
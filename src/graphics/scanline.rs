@@ -0,0 +1,117 @@
+//! Per-scanline register effects
+//!
+//! A `ScanlineTable` holds one register value per visible scanline, which is
+//! streamed into the display registers during each HBlank by DMA. This is the
+//! basis for raster effects such as parallax scrolling, wavy backgrounds and
+//! colour gradients.
+
+use core::mem::size_of;
+
+use crate::dma::{AddressControl, Channel, DmaControl, Timing};
+
+/// Number of visible scanlines
+pub const SCANLINES: usize = 160;
+
+/// Table of per-scanline values for the register (or register block) at
+/// `dest`.
+///
+/// `T` is the type written each scanline, and must be a whole number of
+/// halfwords: `u16` for a single register, or `u32` (or a `#[repr(C)]`
+/// struct of words) to update adjacent registers together. Other sizes
+/// panic on creation.
+///
+/// The DMA channel reads directly from the table, so while armed the table
+/// must not be moved. It is typically placed in a `static`, and re-armed from
+/// a VBlank interrupt handler or after waiting for VBlank.
+pub struct ScanlineTable<T: Copy> {
+    // The HBlank after the last scanline also triggers a transfer, so one
+    // extra entry (a copy of scanline 0) follows the visible scanlines
+    entries: [T; SCANLINES + 1],
+    dest: usize,
+    channel: Channel,
+}
+
+impl<T: Copy> ScanlineTable<T> {
+    /// Create a new table for the register at `dest`, with every scanline
+    /// set to `initial`.
+    ///
+    /// Tables use DMA 0 by default, as this has the highest priority.
+    pub fn new(dest: usize, initial: T) -> ScanlineTable<T> {
+        let size = size_of::<T>();
+        assert!(
+            size != 0 && size % 2 == 0,
+            "scanline values must be a whole number of halfwords"
+        );
+        ScanlineTable {
+            entries: [initial; SCANLINES + 1],
+            dest: dest,
+            channel: Channel::Dma0,
+        }
+    }
+
+    /// Use the DMA channel `channel` for this table
+    pub fn with_channel(mut self, channel: Channel) -> ScanlineTable<T> {
+        self.channel = channel;
+        self
+    }
+
+    /// Set the value for scanline `line`
+    pub fn set(&mut self, line: usize, v: T) {
+        self.entries[line] = v;
+    }
+
+    /// Fetch the value for scanline `line`
+    pub fn get(&self, line: usize) -> T {
+        self.entries[line]
+    }
+
+    /// Fetch all scanline values for editing
+    pub fn entries(&mut self) -> &mut [T] {
+        &mut self.entries[..SCANLINES]
+    }
+
+    /// Fetch the DMA transfer unit count and control setting for one entry
+    fn transfer(&self) -> (u16, DmaControl) {
+        let size = size_of::<T>();
+        match size % 4 {
+            0 => ((size / 4) as u16, DmaControl::new().with_32bit(true)),
+            _ => ((size / 2) as u16, DmaControl::new()),
+        }
+    }
+
+    /// Arm the table for the coming frame.
+    ///
+    /// This writes the value for scanline 0 immediately, then configures an
+    /// HBlank DMA to write each following value before its scanline is
+    /// drawn. It must be called during VBlank, every frame.
+    pub fn rearm(&mut self) {
+        self.entries[SCANLINES] = self.entries[0];
+        let (count, control) = self.transfer();
+        let src = self.entries.as_ptr() as *const u8;
+        let dst = self.dest as *mut u8;
+        unsafe {
+            self.channel.start(src, dst, count, control);
+            self.channel.start(
+                src.add(size_of::<T>()),
+                dst,
+                count,
+                control
+                    .with_dest(AddressControl::IncrementReload)
+                    .with_source(AddressControl::Increment)
+                    .with_repeat(true)
+                    .with_timing(Timing::HBlank),
+            );
+        }
+    }
+
+    /// Stop updating the register.
+    pub fn stop(&self) {
+        self.channel.stop();
+    }
+}
+
+impl<T: Copy> Drop for ScanlineTable<T> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,421 @@
+//! Hardware sprites (objects)
+//!
+//! Object attributes are edited in a shadow copy of OAM held in RAM, which
+//! is then copied to OAM with `ShadowOam::commit` during VBlank.
+
+use embedded_builder::region::Region;
+use gba::io::display::DISPCNT;
+
+use crate::graphics::affine::AffineMatrix;
+use crate::memory::OAM;
+
+/// Number of objects supported by the hardware
+pub const OBJECTS: usize = 128;
+
+/// Number of affine parameter groups supported by the hardware
+pub const AFFINE_GROUPS: usize = 32;
+
+/// Affine parameter group index, allocated with `ShadowOam::alloc_affine`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AffineId(u8);
+
+impl AffineId {
+    /// Fetch the index (0-31) of the affine parameter group
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Object tile mapping, as configured in DISPCNT
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ObjectMapping {
+    /// Tiles are laid out in a 32x32 tile matrix
+    TwoD,
+    /// Tiles are laid out sequentially
+    OneD,
+}
+
+/// Object rendering mode
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ObjectMode {
+    /// Normal rendering
+    Normal = 0,
+    /// Semi-transparent, the object is a first target for alpha blending
+    SemiTransparent = 1,
+    /// The object is not drawn, but masks the object window
+    Window = 2,
+}
+
+/// Object colour depth
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ObjectColour {
+    /// 4 bits per pixel, using one of 16 pallet banks
+    Bpp4 = 0,
+    /// 8 bits per pixel, using the full 256 colour pallet
+    Bpp8 = 1,
+}
+
+/// Object sizes, as `S<width>x<height>` in pixels
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ObjectSize {
+    S8x8,
+    S16x16,
+    S32x32,
+    S64x64,
+    S16x8,
+    S32x8,
+    S32x16,
+    S64x32,
+    S8x16,
+    S8x32,
+    S16x32,
+    S32x64,
+}
+
+impl ObjectSize {
+    /// Fetch the `(shape, size)` fields for this object size
+    fn fields(self) -> (u16, u16) {
+        match self {
+            ObjectSize::S8x8 => (0, 0),
+            ObjectSize::S16x16 => (0, 1),
+            ObjectSize::S32x32 => (0, 2),
+            ObjectSize::S64x64 => (0, 3),
+            ObjectSize::S16x8 => (1, 0),
+            ObjectSize::S32x8 => (1, 1),
+            ObjectSize::S32x16 => (1, 2),
+            ObjectSize::S64x32 => (1, 3),
+            ObjectSize::S8x16 => (2, 0),
+            ObjectSize::S8x32 => (2, 1),
+            ObjectSize::S16x32 => (2, 2),
+            ObjectSize::S32x64 => (2, 3),
+        }
+    }
+
+    /// Build an object size from `(shape, size)` fields
+    fn from_fields(shape: u16, size: u16) -> ObjectSize {
+        match (shape, size) {
+            (1, 0) => ObjectSize::S16x8,
+            (1, 1) => ObjectSize::S32x8,
+            (1, 2) => ObjectSize::S32x16,
+            (1, 3) => ObjectSize::S64x32,
+            (2, 0) => ObjectSize::S8x16,
+            (2, 1) => ObjectSize::S8x32,
+            (2, 2) => ObjectSize::S16x32,
+            (2, 3) => ObjectSize::S32x64,
+            (_, 1) => ObjectSize::S16x16,
+            (_, 2) => ObjectSize::S32x32,
+            (_, 3) => ObjectSize::S64x64,
+            _ => ObjectSize::S8x8,
+        }
+    }
+
+    /// Return the size in pixels as `(width, height)`
+    pub fn dimensions(self) -> (usize, usize) {
+        match self {
+            ObjectSize::S8x8 => (8, 8),
+            ObjectSize::S16x16 => (16, 16),
+            ObjectSize::S32x32 => (32, 32),
+            ObjectSize::S64x64 => (64, 64),
+            ObjectSize::S16x8 => (16, 8),
+            ObjectSize::S32x8 => (32, 8),
+            ObjectSize::S32x16 => (32, 16),
+            ObjectSize::S64x32 => (64, 32),
+            ObjectSize::S8x16 => (8, 16),
+            ObjectSize::S8x32 => (8, 32),
+            ObjectSize::S16x32 => (16, 32),
+            ObjectSize::S32x64 => (32, 64),
+        }
+    }
+
+    /// Return the size in 8x8 tiles as `(width, height)`
+    pub fn tiles(self) -> (usize, usize) {
+        let (w, h) = self.dimensions();
+        (w / 8, h / 8)
+    }
+}
+
+/// Object attributes (attr0, attr1 and attr2) for a single sprite
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ObjectAttributes {
+    attr0: u16,
+    attr1: u16,
+    attr2: u16,
+}
+
+impl ObjectAttributes {
+    /// Create a new 8x8 object at `(0, 0)` using tile 0
+    pub const fn new() -> ObjectAttributes {
+        ObjectAttributes {
+            attr0: 0,
+            attr1: 0,
+            attr2: 0,
+        }
+    }
+
+    /// Create a new hidden object
+    pub const fn hidden() -> ObjectAttributes {
+        ObjectAttributes {
+            attr0: 0x0200,
+            attr1: 0,
+            attr2: 0,
+        }
+    }
+
+    /// Set the position of the top left corner of the object.
+    ///
+    /// Coordinates wrap at 512 horizontally and 256 vertically, so objects
+    /// may be partially off the top or left of the screen.
+    pub fn set_position(&mut self, x: i16, y: i16) {
+        self.attr0 = (self.attr0 & !0x00FF) | (y as u16 & 0x00FF);
+        self.attr1 = (self.attr1 & !0x01FF) | (x as u16 & 0x01FF);
+    }
+
+    /// Fetch the position of the object as `(x, y)`
+    pub fn position(&self) -> (i16, i16) {
+        let x = (self.attr1 & 0x01FF) as i16;
+        let y = (self.attr0 & 0x00FF) as i16;
+        (
+            if x >= 256 { x - 512 } else { x },
+            if y >= 160 { y - 256 } else { y },
+        )
+    }
+
+    /// Set the size (and shape) of the object
+    pub fn set_size(&mut self, size: ObjectSize) {
+        let (shape, size) = size.fields();
+        self.attr0 = (self.attr0 & !0xC000) | (shape << 14);
+        self.attr1 = (self.attr1 & !0xC000) | (size << 14);
+    }
+
+    /// Fetch the size of the object
+    pub fn size(&self) -> ObjectSize {
+        ObjectSize::from_fields(self.attr0 >> 14, self.attr1 >> 14)
+    }
+
+    /// Set the base tile index (0-1023) of the object.
+    ///
+    /// Tile indices are always in units of 32 bytes, so 8bpp objects should
+    /// use even tile indices.
+    pub fn set_tile(&mut self, tile: u16) {
+        self.attr2 = (self.attr2 & !0x03FF) | (tile & 0x03FF);
+    }
+
+    /// Fetch the base tile index of the object
+    pub fn tile(&self) -> u16 {
+        self.attr2 & 0x03FF
+    }
+
+    /// Set the pallet bank (0-15) used by a 4bpp object
+    pub fn set_pallet(&mut self, bank: u8) {
+        self.attr2 = (self.attr2 & !0xF000) | ((bank as u16 & 0x0F) << 12);
+    }
+
+    /// Set the drawing priority (0-3) relative to backgrounds
+    pub fn set_priority(&mut self, priority: u8) {
+        self.attr2 = (self.attr2 & !0x0C00) | ((priority as u16 & 0x03) << 10);
+    }
+
+    /// Set the colour depth of the object
+    pub fn set_colour(&mut self, colour: ObjectColour) {
+        self.attr0 = (self.attr0 & !0x2000) | ((colour as u16) << 13);
+    }
+
+    /// Set the rendering mode of the object
+    pub fn set_mode(&mut self, mode: ObjectMode) {
+        self.attr0 = (self.attr0 & !0x0C00) | ((mode as u16) << 10);
+    }
+
+    /// Set whether the mosaic effect applies to the object
+    pub fn set_mosaic(&mut self, mosaic: bool) {
+        self.attr0 = (self.attr0 & !0x1000) | ((mosaic as u16) << 12);
+    }
+
+    /// Flip the object horizontally.
+    ///
+    /// The flip bits are shared with the affine parameter index, so this is
+    /// only valid for non-affine objects.
+    pub fn set_hflip(&mut self, flip: bool) {
+        self.attr1 = (self.attr1 & !0x1000) | ((flip as u16) << 12);
+    }
+
+    /// Flip the object vertically.
+    ///
+    /// The flip bits are shared with the affine parameter index, so this is
+    /// only valid for non-affine objects.
+    pub fn set_vflip(&mut self, flip: bool) {
+        self.attr1 = (self.attr1 & !0x2000) | ((flip as u16) << 13);
+    }
+
+    /// Hide or show the object.
+    ///
+    /// Hiding an affine object returns it to normal (non-affine) rendering.
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.attr0 = (self.attr0 & !0x0300) | ((hidden as u16) << 9);
+    }
+
+    /// Check whether the object is hidden
+    pub fn is_hidden(&self) -> bool {
+        self.attr0 & 0x0300 == 0x0200
+    }
+
+    /// Render the object with the affine parameter group `id`.
+    ///
+    /// With `double_size` set the object is drawn into a bounding box twice
+    /// its size, so rotated or enlarged objects are not clipped.
+    pub fn set_affine(&mut self, id: AffineId, double_size: bool) {
+        self.attr0 = (self.attr0 & !0x0300) | 0x0100 | ((double_size as u16) << 9);
+        self.attr1 = (self.attr1 & !0x3E00) | ((id.0 as u16 & 0x1F) << 9);
+    }
+
+    /// Return the object to normal (non-affine) rendering
+    pub fn clear_affine(&mut self) {
+        self.attr0 &= !0x0300;
+        self.attr1 &= !0x3E00;
+    }
+
+    /// Fetch the affine parameter group used by the object, if any
+    pub fn affine(&self) -> Option<AffineId> {
+        match self.attr0 & 0x0100 {
+            0 => None,
+            _ => Some(AffineId(((self.attr1 >> 9) & 0x1F) as u8)),
+        }
+    }
+
+    /// Check whether the object is an affine object drawn at double size
+    pub fn is_double_size(&self) -> bool {
+        self.attr0 & 0x0300 == 0x0300
+    }
+
+    /// Position the object so that it is centred on `(x, y)`.
+    ///
+    /// This accounts for the larger bounding box of double size objects.
+    pub fn set_centre(&mut self, x: i16, y: i16) {
+        let (w, h) = self.size().dimensions();
+        let (w, h) = match self.is_double_size() {
+            true => (w as i16 * 2, h as i16 * 2),
+            false => (w as i16, h as i16),
+        };
+        self.set_position(x - w / 2, y - h / 2);
+    }
+}
+
+/// OAM entry, an object's attributes interleaved with one affine parameter
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct OamEntry {
+    attrs: ObjectAttributes,
+    affine: i16,
+}
+
+/// Shadow copy of object attribute memory
+///
+/// The 32 affine parameter groups are interleaved with the object
+/// attributes, each group occupying the spare halfword of four consecutive
+/// objects.
+pub struct ShadowOam {
+    entries: [OamEntry; OBJECTS],
+    affine_used: u32,
+    oam: Region<u16>,
+}
+
+impl ShadowOam {
+    /// Create a new shadow OAM, with all objects hidden
+    pub fn new() -> ShadowOam {
+        ShadowOam {
+            entries: [OamEntry {
+                attrs: ObjectAttributes::hidden(),
+                affine: 0,
+            }; OBJECTS],
+            affine_used: 0,
+            oam: Region::from(OAM),
+        }
+    }
+
+    /// Fetch the attributes of the object `i`
+    pub fn object(&self, i: usize) -> &ObjectAttributes {
+        &self.entries[i].attrs
+    }
+
+    /// Fetch the attributes of the object `i` for editing
+    pub fn object_mut(&mut self, i: usize) -> &mut ObjectAttributes {
+        &mut self.entries[i].attrs
+    }
+
+    /// Hide all objects
+    pub fn hide_all(&mut self) {
+        for e in self.entries.iter_mut() {
+            e.attrs.set_hidden(true);
+        }
+    }
+
+    /// Allocate an affine parameter group, initialised to the identity
+    /// matrix, returning `None` if all groups are in use.
+    pub fn alloc_affine(&mut self) -> Option<AffineId> {
+        for i in 0..AFFINE_GROUPS {
+            if self.affine_used & (1 << i) == 0 {
+                self.affine_used |= 1 << i;
+                let id = AffineId(i as u8);
+                self.set_affine(id, &AffineMatrix::identity());
+                return Some(id);
+            }
+        }
+        None
+    }
+
+    /// Release the affine parameter group `id`.
+    ///
+    /// Any objects still using the group must be updated by the caller.
+    pub fn free_affine(&mut self, id: AffineId) {
+        self.affine_used &= !(1 << id.0);
+    }
+
+    /// Set the matrix for the affine parameter group `id`
+    pub fn set_affine(&mut self, id: AffineId, m: &AffineMatrix) {
+        let base = id.index() * 4;
+        self.entries[base + 0].affine = m.pa;
+        self.entries[base + 1].affine = m.pb;
+        self.entries[base + 2].affine = m.pc;
+        self.entries[base + 3].affine = m.pd;
+    }
+
+    /// Fetch the matrix for the affine parameter group `id`
+    pub fn affine(&self, id: AffineId) -> AffineMatrix {
+        let base = id.index() * 4;
+        AffineMatrix::new(
+            self.entries[base + 0].affine,
+            self.entries[base + 1].affine,
+            self.entries[base + 2].affine,
+            self.entries[base + 3].affine,
+        )
+    }
+
+    /// Copy the shadow buffer into OAM.
+    ///
+    /// OAM may only be written during VBlank (or HBlank with the HBlank
+    /// interval free flag set), so this should be called after waiting for
+    /// VBlank.
+    pub fn commit(&mut self) {
+        for (i, e) in self.entries.iter().enumerate() {
+            self.oam.write_index(i * 4 + 0, e.attrs.attr0);
+            self.oam.write_index(i * 4 + 1, e.attrs.attr1);
+            self.oam.write_index(i * 4 + 2, e.attrs.attr2);
+            self.oam.write_index(i * 4 + 3, e.affine as u16);
+        }
+    }
+}
+
+/// Enable object rendering with the tile mapping `mapping`.
+pub fn enable(mapping: ObjectMapping) {
+    let dispcnt = DISPCNT
+        .read()
+        .with_obj(true)
+        .with_oam_memory_1d(mapping == ObjectMapping::OneD);
+    DISPCNT.write(dispcnt);
+}
+
+/// Disable object rendering.
+pub fn disable() {
+    let dispcnt = DISPCNT.read().with_obj(false);
+    DISPCNT.write(dispcnt);
+}
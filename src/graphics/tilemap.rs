@@ -1,13 +1,565 @@
 //! Tile map rendering modes
 
-use crate::memory::{KB, PALRAM, VRAM};
+use core::ptr;
+
 use embedded_builder::region::Region;
+use gba::io::display::{DisplayMode, DISPCNT};
+
+use crate::graphics;
+use crate::graphics::affine::AffineMatrix;
+use crate::memory::{
+    KB, PALRAM, REG_BG0CNT, REG_BG0HOFS, REG_BG0VOFS, REG_BG1CNT, REG_BG1HOFS, REG_BG1VOFS,
+    REG_BG2CNT, REG_BG2HOFS, REG_BG2PA, REG_BG2VOFS, REG_BG2X, REG_BG3CNT, REG_BG3HOFS, REG_BG3PA,
+    REG_BG3VOFS, REG_BG3X, VRAM,
+};
 
 pub trait Tile {}
 
 pub type TileSingle = [u8; 32];
 pub type TileDouble = [u8; 64];
 
+/// Size of a background character block (tile data)
+pub const CHARBLOCK_SIZE: usize = 16 * KB;
+
+/// Size of a background screen block (map data)
+pub const SCREENBLOCK_SIZE: usize = 2 * KB;
+
+/// Background control registers, indexed by background
+const BGCNT: [usize; 4] = [REG_BG0CNT, REG_BG1CNT, REG_BG2CNT, REG_BG3CNT];
+
+/// Background scroll registers as `(horizontal, vertical)`, indexed by background
+const BGOFS: [(usize, usize); 4] = [
+    (REG_BG0HOFS, REG_BG0VOFS),
+    (REG_BG1HOFS, REG_BG1VOFS),
+    (REG_BG2HOFS, REG_BG2VOFS),
+    (REG_BG3HOFS, REG_BG3VOFS),
+];
+
+/// Affine parameter registers (PA, with PB-PD following), indexed by
+/// affine background (BG2, BG3)
+const BGPA: [usize; 2] = [REG_BG2PA, REG_BG3PA];
+
+/// Affine reference point registers (X, with Y following), indexed by
+/// affine background (BG2, BG3)
+const BGX: [usize; 2] = [REG_BG2X, REG_BG3X];
+
+/// Background layers
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Bg {
+    BG0 = 0,
+    BG1 = 1,
+    BG2 = 2,
+    BG3 = 3,
+}
+
+/// Background colour depth
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ColourDepth {
+    /// 4 bits per pixel, 16 pallet banks of 16 colours
+    Bpp4 = 0,
+    /// 8 bits per pixel, one pallet of 256 colours
+    Bpp8 = 1,
+}
+
+/// Text (regular) background sizes, in tiles
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextSize {
+    S32x32 = 0,
+    S64x32 = 1,
+    S32x64 = 2,
+    S64x64 = 3,
+}
+
+/// Affine background sizes, in tiles
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AffineSize {
+    S16x16 = 0,
+    S32x32 = 1,
+    S64x64 = 2,
+    S128x128 = 3,
+}
+
+/// Background control (BGxCNT) setting
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BackgroundControl(u16);
+
+impl BackgroundControl {
+    /// Create a new background control setting, with all fields zeroed
+    pub const fn new() -> BackgroundControl {
+        BackgroundControl(0)
+    }
+
+    /// Set the drawing priority (0 is drawn on top, 3 at the bottom)
+    pub const fn with_priority(self, priority: u8) -> BackgroundControl {
+        BackgroundControl((self.0 & !0x0003) | (priority as u16 & 0x03))
+    }
+
+    /// Set the character block (0-3) containing the tile data
+    pub const fn with_charblock(self, block: u8) -> BackgroundControl {
+        BackgroundControl((self.0 & !0x000C) | ((block as u16 & 0x03) << 2))
+    }
+
+    /// Set whether the mosaic effect applies to the background
+    pub const fn with_mosaic(self, mosaic: bool) -> BackgroundControl {
+        BackgroundControl((self.0 & !0x0040) | ((mosaic as u16) << 6))
+    }
+
+    /// Set the tile colour depth
+    pub const fn with_colour(self, depth: ColourDepth) -> BackgroundControl {
+        BackgroundControl((self.0 & !0x0080) | ((depth as u16) << 7))
+    }
+
+    /// Set the screen block (0-31) containing the map data
+    pub const fn with_screenblock(self, block: u8) -> BackgroundControl {
+        BackgroundControl((self.0 & !0x1F00) | ((block as u16 & 0x1F) << 8))
+    }
+
+    /// Set whether the map wraps around at the edges (affine backgrounds only)
+    pub const fn with_wraparound(self, wrap: bool) -> BackgroundControl {
+        BackgroundControl((self.0 & !0x2000) | ((wrap as u16) << 13))
+    }
+
+    /// Set the map size for a text background
+    pub const fn with_text_size(self, size: TextSize) -> BackgroundControl {
+        BackgroundControl((self.0 & !0xC000) | ((size as u16) << 14))
+    }
+
+    /// Set the map size for an affine background
+    pub const fn with_affine_size(self, size: AffineSize) -> BackgroundControl {
+        BackgroundControl((self.0 & !0xC000) | ((size as u16) << 14))
+    }
+
+    /// Fetch the raw register value
+    pub const fn bits(&self) -> u16 {
+        self.0
+    }
+}
+
+/// Text background map entry
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ScreenEntry(u16);
+
+impl ScreenEntry {
+    /// Create a map entry for the tile `tile` (0-1023)
+    pub const fn new(tile: u16) -> ScreenEntry {
+        ScreenEntry(tile & 0x03FF)
+    }
+
+    /// Flip the tile horizontally
+    pub const fn with_hflip(self, flip: bool) -> ScreenEntry {
+        ScreenEntry((self.0 & !0x0400) | ((flip as u16) << 10))
+    }
+
+    /// Flip the tile vertically
+    pub const fn with_vflip(self, flip: bool) -> ScreenEntry {
+        ScreenEntry((self.0 & !0x0800) | ((flip as u16) << 11))
+    }
+
+    /// Set the pallet bank (0-15) used by a 4bpp tile
+    pub const fn with_pallet(self, bank: u8) -> ScreenEntry {
+        ScreenEntry((self.0 & !0xF000) | ((bank as u16 & 0x0F) << 12))
+    }
+
+    /// Fetch the raw map entry value
+    pub const fn bits(&self) -> u16 {
+        self.0
+    }
+}
+
+/// Enable or disable the background `bg` in DISPCNT
+fn set_bg_enabled(bg: Bg, enabled: bool) {
+    let dispcnt = DISPCNT.read();
+    let dispcnt = match bg {
+        Bg::BG0 => dispcnt.with_bg0(enabled),
+        Bg::BG1 => dispcnt.with_bg1(enabled),
+        Bg::BG2 => dispcnt.with_bg2(enabled),
+        Bg::BG3 => dispcnt.with_bg3(enabled),
+    };
+    DISPCNT.write(dispcnt);
+}
+
+/// Write the control setting for the background `bg`
+fn write_control(bg: Bg, control: BackgroundControl) {
+    unsafe { ptr::write_volatile(BGCNT[bg as usize] as *mut u16, control.0) }
+}
+
+/// Write the affine matrix `m` for the affine background `bg` (BG2 or BG3)
+pub(crate) fn write_matrix(bg: Bg, m: &AffineMatrix) {
+    let pa = BGPA[bg as usize - 2] as *mut i16;
+    unsafe {
+        ptr::write_volatile(pa.offset(0), m.pa);
+        ptr::write_volatile(pa.offset(1), m.pb);
+        ptr::write_volatile(pa.offset(2), m.pc);
+        ptr::write_volatile(pa.offset(3), m.pd);
+    }
+}
+
+/// Write the reference point `(x, y)` (20.8 fixed point) for the affine
+/// background `bg` (BG2 or BG3)
+pub(crate) fn write_reference(bg: Bg, x: i32, y: i32) {
+    let rx = BGX[bg as usize - 2] as *mut i32;
+    unsafe {
+        ptr::write_volatile(rx.offset(0), x & 0x0FFF_FFFF);
+        ptr::write_volatile(rx.offset(1), y & 0x0FFF_FFFF);
+    }
+}
+
+/// Text (regular, scrolling) background
+#[derive(Debug, PartialEq)]
+pub struct TextBackground {
+    bg: Bg,
+    control: BackgroundControl,
+    scroll: (u16, u16),
+}
+
+impl TextBackground {
+    fn new(bg: Bg) -> TextBackground {
+        TextBackground {
+            bg: bg,
+            control: BackgroundControl::new(),
+            scroll: (0, 0),
+        }
+    }
+
+    /// Fetch the background layer
+    pub fn bg(&self) -> Bg {
+        self.bg
+    }
+
+    /// Show the background.
+    pub fn enable(&mut self) {
+        set_bg_enabled(self.bg, true);
+    }
+
+    /// Hide the background.
+    pub fn disable(&mut self) {
+        set_bg_enabled(self.bg, false);
+    }
+
+    /// Set the background control register.
+    pub fn set_control(&mut self, control: BackgroundControl) {
+        self.control = control;
+        write_control(self.bg, control);
+    }
+
+    /// Fetch the current background control setting
+    pub fn control(&self) -> BackgroundControl {
+        self.control
+    }
+
+    /// Enable or disable the mosaic effect for the background.
+    pub fn set_mosaic(&mut self, mosaic: bool) {
+        let control = self.control.with_mosaic(mosaic);
+        self.set_control(control);
+    }
+
+    /// Set the scroll offset of the background to `(x, y)`.
+    pub fn set_scroll(&mut self, x: u16, y: u16) {
+        self.scroll = (x, y);
+        let (hofs, vofs) = BGOFS[self.bg as usize];
+        unsafe {
+            ptr::write_volatile(hofs as *mut u16, x & 0x01FF);
+            ptr::write_volatile(vofs as *mut u16, y & 0x01FF);
+        }
+    }
+
+    /// Fetch the current scroll offset as `(x, y)`.
+    ///
+    /// The scroll registers are write only, so this returns the last value
+    /// set with `set_scroll`.
+    pub fn scroll(&self) -> (u16, u16) {
+        self.scroll
+    }
+}
+
+/// Affine (rotation / scaling) background
+#[derive(Debug, PartialEq)]
+pub struct AffineBackground {
+    bg: Bg,
+    control: BackgroundControl,
+    matrix: AffineMatrix,
+    reference: (i32, i32),
+}
+
+impl AffineBackground {
+    fn new(bg: Bg) -> AffineBackground {
+        AffineBackground {
+            bg: bg,
+            control: BackgroundControl::new(),
+            matrix: AffineMatrix::identity(),
+            reference: (0, 0),
+        }
+    }
+
+    /// Fetch the background layer
+    pub fn bg(&self) -> Bg {
+        self.bg
+    }
+
+    /// Write the affine matrix and reference point to the registers, which
+    /// may hold values left by another mode (such as `Mode5` scaling)
+    fn write_affine(&self) {
+        write_matrix(self.bg, &self.matrix);
+        write_reference(self.bg, self.reference.0, self.reference.1);
+    }
+
+    /// Show the background.
+    pub fn enable(&mut self) {
+        set_bg_enabled(self.bg, true);
+    }
+
+    /// Hide the background.
+    pub fn disable(&mut self) {
+        set_bg_enabled(self.bg, false);
+    }
+
+    /// Set the background control register.
+    ///
+    /// Affine backgrounds always use 8bpp tiles, so the colour depth is
+    /// ignored by the hardware.
+    pub fn set_control(&mut self, control: BackgroundControl) {
+        self.control = control;
+        write_control(self.bg, control);
+    }
+
+    /// Fetch the current background control setting
+    pub fn control(&self) -> BackgroundControl {
+        self.control
+    }
+
+    /// Enable or disable the mosaic effect for the background.
+    pub fn set_mosaic(&mut self, mosaic: bool) {
+        let control = self.control.with_mosaic(mosaic);
+        self.set_control(control);
+    }
+
+    /// Set the affine matrix mapping screen space to map space.
+    pub fn set_matrix(&mut self, m: &AffineMatrix) {
+        self.matrix = *m;
+        write_matrix(self.bg, m);
+    }
+
+    /// Fetch the current affine matrix
+    ///
+    /// The affine registers are write only, so this returns the last value
+    /// set with `set_matrix`.
+    pub fn matrix(&self) -> AffineMatrix {
+        self.matrix
+    }
+
+    /// Set the reference point, the map position (in 20.8 fixed point)
+    /// displayed at the top left of the screen.
+    ///
+    /// The hardware latches the reference point at the start of each frame,
+    /// while writes during the frame take effect from the next scanline.
+    pub fn set_reference(&mut self, x: i32, y: i32) {
+        self.reference = (x, y);
+        write_reference(self.bg, x, y);
+    }
+
+    /// Fetch the current reference point as `(x, y)` in 20.8 fixed point
+    pub fn reference(&self) -> (i32, i32) {
+        self.reference
+    }
+
+    /// Rotate counter-clockwise by `angle` and scale by `scale` (8.8 fixed
+    /// point, as `(x, y)`) around the map pixel `map`, which is displayed
+    /// at the screen pixel `screen`.
+    ///
+    /// Scales are handled as by `AffineMatrix::rotate_scale`, so a scale of
+    /// 0 does not fault.
+    pub fn rotate_scale_around(
+        &mut self,
+        map: (i32, i32),
+        screen: (i32, i32),
+        angle: u16,
+        scale: (i32, i32),
+    ) {
+        let m = AffineMatrix::rotate_scale(angle, scale.0, scale.1);
+        // Walk back from the map point to the top left of the screen
+        let dx = m.pa as i32 * screen.0 + m.pb as i32 * screen.1;
+        let dy = m.pc as i32 * screen.0 + m.pd as i32 * screen.1;
+        self.set_matrix(&m);
+        self.set_reference((map.0 << 8) - dx, (map.1 << 8) - dy);
+    }
+}
+
+/// Tiled mode trait, implemented by tile map rendering modes
+pub trait TiledMode {
+    /// Create a new instance of the rendering mode.
+    fn new() -> Self;
+
+    /// Enables the rendering mode.
+    ///
+    /// Backgrounds are disabled until enabled individually.
+    fn enable(&mut self);
+
+    /// Load `tiles` into the character block `block`, starting at the tile
+    /// index `index` (in 4bpp, 32-byte tiles).
+    fn load_tiles(&mut self, block: usize, index: usize, tiles: &[TileSingle]) {
+        let mut vram: Region<u16> =
+            Region::new(VRAM.0 + block * CHARBLOCK_SIZE, CHARBLOCK_SIZE / 2);
+        for (i, t) in tiles.iter().enumerate() {
+            let base = (index + i) * t.len() / 2;
+            for j in 0..t.len() / 2 {
+                vram.write_index(base + j, t[j * 2] as u16 | (t[j * 2 + 1] as u16) << 8);
+            }
+        }
+    }
+
+    /// Load text background map `entries` into the screen block `block`,
+    /// starting at the entry `index`.
+    fn load_map(&mut self, block: usize, index: usize, entries: &[ScreenEntry]) {
+        let mut vram: Region<u16> =
+            Region::new(VRAM.0 + block * SCREENBLOCK_SIZE, SCREENBLOCK_SIZE / 2);
+        for (i, e) in entries.iter().enumerate() {
+            vram.write_index(index + i, e.0);
+        }
+    }
+
+    /// Load affine background map `entries` (one byte per tile) into the
+    /// screen block `block`, starting at the entry `index`.
+    fn load_affine_map(&mut self, block: usize, index: usize, entries: &[u8]) {
+        let mut vram: Region<u16> =
+            Region::new(VRAM.0 + block * SCREENBLOCK_SIZE, SCREENBLOCK_SIZE / 2);
+        // VRAM does not support byte writes, so entries are packed in pairs
+        for (i, e) in entries.iter().enumerate() {
+            let n = index + i;
+            let v: u16 = *vram.read_index(n / 2);
+            let v = if n % 2 == 0 {
+                (v & 0xFF00) | *e as u16
+            } else {
+                (v & 0x00FF) | ((*e as u16) << 8)
+            };
+            vram.write_index(n / 2, v);
+        }
+    }
+}
+
+/// Graphics mode 0 - four text backgrounds
+#[derive(Debug, PartialEq)]
+pub struct Mode0 {
+    bg0: TextBackground,
+    bg1: TextBackground,
+    bg2: TextBackground,
+    bg3: TextBackground,
+}
+
+impl TiledMode for Mode0 {
+    fn new() -> Mode0 {
+        Mode0 {
+            bg0: TextBackground::new(Bg::BG0),
+            bg1: TextBackground::new(Bg::BG1),
+            bg2: TextBackground::new(Bg::BG2),
+            bg3: TextBackground::new(Bg::BG3),
+        }
+    }
+
+    fn enable(&mut self) {
+        let dispcnt = graphics::display_control(DisplayMode::Mode0);
+        DISPCNT.write(dispcnt);
+    }
+}
+
+impl Mode0 {
+    /// Fetch text background 0
+    pub fn bg0(&mut self) -> &mut TextBackground {
+        &mut self.bg0
+    }
+
+    /// Fetch text background 1
+    pub fn bg1(&mut self) -> &mut TextBackground {
+        &mut self.bg1
+    }
+
+    /// Fetch text background 2
+    pub fn bg2(&mut self) -> &mut TextBackground {
+        &mut self.bg2
+    }
+
+    /// Fetch text background 3
+    pub fn bg3(&mut self) -> &mut TextBackground {
+        &mut self.bg3
+    }
+}
+
+/// Graphics mode 1 - two text backgrounds (BG0, BG1) and one affine
+/// background (BG2)
+#[derive(Debug, PartialEq)]
+pub struct Mode1 {
+    bg0: TextBackground,
+    bg1: TextBackground,
+    bg2: AffineBackground,
+}
+
+impl TiledMode for Mode1 {
+    fn new() -> Mode1 {
+        Mode1 {
+            bg0: TextBackground::new(Bg::BG0),
+            bg1: TextBackground::new(Bg::BG1),
+            bg2: AffineBackground::new(Bg::BG2),
+        }
+    }
+
+    fn enable(&mut self) {
+        let dispcnt = graphics::display_control(DisplayMode::Mode1);
+        DISPCNT.write(dispcnt);
+        self.bg2.write_affine();
+    }
+}
+
+impl Mode1 {
+    /// Fetch text background 0
+    pub fn bg0(&mut self) -> &mut TextBackground {
+        &mut self.bg0
+    }
+
+    /// Fetch text background 1
+    pub fn bg1(&mut self) -> &mut TextBackground {
+        &mut self.bg1
+    }
+
+    /// Fetch affine background 2
+    pub fn bg2(&mut self) -> &mut AffineBackground {
+        &mut self.bg2
+    }
+}
+
+/// Graphics mode 2 - two affine backgrounds (BG2, BG3)
+#[derive(Debug, PartialEq)]
+pub struct Mode2 {
+    bg2: AffineBackground,
+    bg3: AffineBackground,
+}
+
+impl TiledMode for Mode2 {
+    fn new() -> Mode2 {
+        Mode2 {
+            bg2: AffineBackground::new(Bg::BG2),
+            bg3: AffineBackground::new(Bg::BG3),
+        }
+    }
+
+    fn enable(&mut self) {
+        let dispcnt = graphics::display_control(DisplayMode::Mode2);
+        DISPCNT.write(dispcnt);
+        self.bg2.write_affine();
+        self.bg3.write_affine();
+    }
+}
+
+impl Mode2 {
+    /// Fetch affine background 2
+    pub fn bg2(&mut self) -> &mut AffineBackground {
+        &mut self.bg2
+    }
+
+    /// Fetch affine background 3
+    pub fn bg3(&mut self) -> &mut AffineBackground {
+        &mut self.bg3
+    }
+}
+
 pub struct Mode6 {
     background_blocks: [Region<TileSingle>; 4],
     sprite_blocks: [Region<TileSingle>; 2],
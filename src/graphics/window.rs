@@ -0,0 +1,110 @@
+//! Window layers
+//!
+//! Windows restrict which layers (and whether blending) are displayed in
+//! regions of the screen. WIN0 and WIN1 are rectangles, the object window is
+//! the shape of all objects using `ObjectMode::Window`, and the outside
+//! window covers everything not inside an enabled window.
+
+use core::ptr;
+
+use gba::io::display::DISPCNT;
+
+use crate::graphics::Layer;
+use crate::memory::{REG_WIN0H, REG_WIN0V, REG_WIN1H, REG_WIN1V, REG_WININ, REG_WINOUT};
+
+/// Display windows
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Window {
+    /// Rectangular window 0, highest priority
+    Win0,
+    /// Rectangular window 1
+    Win1,
+    /// Object window, the shape of objects in window mode
+    Object,
+    /// Everything outside the enabled windows
+    Outside,
+}
+
+/// Window control
+#[derive(Debug, PartialEq)]
+pub struct Windows {
+    win_in: u16,
+    win_out: u16,
+}
+
+impl Windows {
+    /// Create a new window controller, with all windows disabled and every
+    /// layer displayed within them
+    pub fn new() -> Windows {
+        let mut w = Windows {
+            win_in: 0x3F3F,
+            win_out: 0x3F3F,
+        };
+        w.write();
+        w
+    }
+
+    /// Write the shadow registers to hardware
+    fn write(&mut self) {
+        unsafe {
+            ptr::write_volatile(REG_WININ as *mut u16, self.win_in);
+            ptr::write_volatile(REG_WINOUT as *mut u16, self.win_out);
+        }
+    }
+
+    /// Set the rectangle of `window` from `(left, top)` up to (but not
+    /// including) `(right, bottom)`.
+    ///
+    /// This only applies to WIN0 and WIN1.
+    pub fn set_rect(&mut self, window: Window, left: u8, top: u8, right: u8, bottom: u8) {
+        let (h, v) = match window {
+            Window::Win0 => (REG_WIN0H, REG_WIN0V),
+            Window::Win1 => (REG_WIN1H, REG_WIN1V),
+            _ => return,
+        };
+        unsafe {
+            ptr::write_volatile(h as *mut u16, ((left as u16) << 8) | right as u16);
+            ptr::write_volatile(v as *mut u16, ((top as u16) << 8) | bottom as u16);
+        }
+    }
+
+    /// Set the `layers` displayed within `window`, and whether colour special
+    /// effects (blending) apply within it.
+    ///
+    /// The backdrop is always displayed, so `Layer::Backdrop` is ignored.
+    pub fn set_layers(&mut self, window: Window, layers: &[Layer], blend: bool) {
+        let v = (Layer::mask(layers) & 0x1F) | ((blend as u16) << 5);
+        match window {
+            Window::Win0 => self.win_in = (self.win_in & 0xFF00) | v,
+            Window::Win1 => self.win_in = (self.win_in & 0x00FF) | (v << 8),
+            Window::Outside => self.win_out = (self.win_out & 0xFF00) | v,
+            Window::Object => self.win_out = (self.win_out & 0x00FF) | (v << 8),
+        }
+        self.write();
+    }
+
+    /// Enable `window` in DISPCNT.
+    ///
+    /// The outside window is active whenever any other window is enabled, so
+    /// enabling it has no effect.
+    pub fn enable(&mut self, window: Window) {
+        set_window_enabled(window, true);
+    }
+
+    /// Disable `window` in DISPCNT.
+    pub fn disable(&mut self, window: Window) {
+        set_window_enabled(window, false);
+    }
+}
+
+/// Enable or disable the window `window` in DISPCNT
+fn set_window_enabled(window: Window, enabled: bool) {
+    let dispcnt = DISPCNT.read();
+    let dispcnt = match window {
+        Window::Win0 => dispcnt.with_win0(enabled),
+        Window::Win1 => dispcnt.with_win1(enabled),
+        Window::Object => dispcnt.with_obj_window(enabled),
+        Window::Outside => return,
+    };
+    DISPCNT.write(dispcnt);
+}
@@ -0,0 +1,212 @@
+//! Interrupt handling
+//!
+//! Interrupts are dispatched by `isr_master` to per-source handlers, which
+//! are registered with [`set_handler`] and switched on with [`enable`].
+//! Nothing is delivered until the master enable is also set with
+//! [`enable_master`].
+
+use core::ptr;
+
+use gba::io::display::DISPSTAT;
+
+use crate::input::Keys;
+use crate::memory::{REG_DISPSTAT, REG_IE, REG_IME, REG_KEYCNT};
+
+/// Interrupt sources, as bits in the IE/IF registers
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interrupt {
+    VBlank = 1 << 0,
+    HBlank = 1 << 1,
+    VCount = 1 << 2,
+    Timer0 = 1 << 3,
+    Timer1 = 1 << 4,
+    Timer2 = 1 << 5,
+    Timer3 = 1 << 6,
+    Serial = 1 << 7,
+    Dma0 = 1 << 8,
+    Dma1 = 1 << 9,
+    Dma2 = 1 << 10,
+    Dma3 = 1 << 11,
+    Keypad = 1 << 12,
+    GamePak = 1 << 13,
+}
+
+/// Number of interrupt sources
+const SOURCES: usize = 14;
+
+/// Interrupt handler function
+pub type Handler = fn();
+
+/// Registered handlers, indexed by interrupt bit
+static mut HANDLERS: [Option<Handler>; SOURCES] = [None; SOURCES];
+
+/// Number of VBlank interrupts delivered
+static mut FRAMES: u32 = 0;
+
+extern "C" {
+    /// ARM interrupt entry stub (see `gba_isr.s`)
+    fn _isr_stub();
+
+    /// BIOS interrupt flags, checked by `IntrWait` and `VBlankIntrWait`
+    static mut __irq_flags: u16;
+
+    /// BIOS interrupt vector, called by the BIOS on any interrupt
+    static mut __irq_vector: u32;
+}
+
+impl Interrupt {
+    /// Index of this interrupt in the handler table
+    fn index(self) -> usize {
+        (self as u16).trailing_zeros() as usize
+    }
+}
+
+/// Install the interrupt stub in the BIOS interrupt vector.
+///
+/// This is called on reset, prior to `main`.
+pub unsafe fn init() {
+    ptr::write_volatile(REG_IME as *mut u16, 0);
+    ptr::write_volatile(&mut __irq_vector as *mut u32, _isr_stub as usize as u32);
+}
+
+/// Run `f` with interrupts masked, restoring the previous master enable
+/// state on completion.
+pub fn free<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    unsafe {
+        let ime = ptr::read_volatile(REG_IME as *const u16);
+        ptr::write_volatile(REG_IME as *mut u16, 0);
+        let r = f();
+        ptr::write_volatile(REG_IME as *mut u16, ime);
+        r
+    }
+}
+
+/// Set (or clear, with `None`) the handler for the interrupt `irq`.
+pub fn set_handler(irq: Interrupt, handler: Option<Handler>) {
+    free(|| unsafe {
+        HANDLERS[irq.index()] = handler;
+    })
+}
+
+/// Enable the interrupt `irq`.
+///
+/// For the display interrupts (VBlank, HBlank and VCount) this also sets the
+/// request bit in DISPSTAT, and for the keypad interrupt the request bit in
+/// KEYCNT (see `set_keypad_irq`). Other sources must also request interrupts
+/// in their own control registers.
+pub fn enable(irq: Interrupt) {
+    free(|| {
+        set_request(irq, true);
+        unsafe {
+            let ie = ptr::read_volatile(REG_IE as *const u16);
+            ptr::write_volatile(REG_IE as *mut u16, ie | irq as u16);
+        }
+    })
+}
+
+/// Disable the interrupt `irq`.
+pub fn disable(irq: Interrupt) {
+    free(|| {
+        set_request(irq, false);
+        unsafe {
+            let ie = ptr::read_volatile(REG_IE as *const u16);
+            ptr::write_volatile(REG_IE as *mut u16, ie & !(irq as u16));
+        }
+    })
+}
+
+/// Check whether the interrupt `irq` is enabled
+pub fn is_enabled(irq: Interrupt) -> bool {
+    unsafe { ptr::read_volatile(REG_IE as *const u16) & irq as u16 != 0 }
+}
+
+/// Set the scanline on which the VCount interrupt is raised.
+pub fn set_vcount(line: u8) {
+    free(|| unsafe {
+        let dispstat = ptr::read_volatile(REG_DISPSTAT as *const u16);
+        ptr::write_volatile(
+            REG_DISPSTAT as *mut u16,
+            (dispstat & 0x00FF) | ((line as u16) << 8),
+        );
+    })
+}
+
+/// Set the keys which raise the keypad interrupt, when any of `keys` is
+/// pressed, or with `all` only when all of them are pressed together.
+pub fn set_keypad_irq(keys: &[Keys], all: bool) {
+    let mask = keys.iter().fold(0, |m, k| m | *k as u16);
+    free(|| unsafe {
+        let keycnt = ptr::read_volatile(REG_KEYCNT as *const u16);
+        let condition = if all { 1 << 15 } else { 0 };
+        ptr::write_volatile(
+            REG_KEYCNT as *mut u16,
+            (keycnt & (1 << 14)) | condition | mask,
+        );
+    })
+}
+
+/// Enable delivery of interrupts (IME).
+pub fn enable_master() {
+    unsafe { ptr::write_volatile(REG_IME as *mut u16, 1) }
+}
+
+/// Disable delivery of interrupts (IME).
+pub fn disable_master() {
+    unsafe { ptr::write_volatile(REG_IME as *mut u16, 0) }
+}
+
+/// Fetch the number of frames displayed, counted by the VBlank interrupt.
+///
+/// This only advances while the VBlank interrupt is enabled, such as after
+/// the first call to `graphics::wait_for_vblank`.
+pub fn frame_count() -> u32 {
+    unsafe { ptr::read_volatile(&FRAMES as *const u32) }
+}
+
+/// Update the interrupt request bit for the display interrupts (in DISPSTAT)
+/// and the keypad interrupt (in KEYCNT)
+fn set_request(irq: Interrupt, enabled: bool) {
+    if irq == Interrupt::Keypad {
+        unsafe {
+            let keycnt = ptr::read_volatile(REG_KEYCNT as *const u16) & !(1 << 14);
+            let request = if enabled { 1 << 14 } else { 0 };
+            ptr::write_volatile(REG_KEYCNT as *mut u16, keycnt | request);
+        }
+        return;
+    }
+    let dispstat = DISPSTAT.read();
+    let dispstat = match irq {
+        Interrupt::VBlank => dispstat.with_vblank_irq_enable(enabled),
+        Interrupt::HBlank => dispstat.with_hblank_irq_enable(enabled),
+        Interrupt::VCount => dispstat.with_vcounter_irq_enable(enabled),
+        _ => return,
+    };
+    DISPSTAT.write(dispstat);
+}
+
+/// Call the handlers for the interrupts `flags`.
+///
+/// This is called from `isr_master`, after the interrupt stub has
+/// acknowledged the interrupts in IF and cleared IME. Interrupts remain
+/// masked while handlers run, so handlers are not nested.
+pub unsafe fn dispatch(flags: u16) {
+    // Flag the interrupts for the BIOS wait functions
+    let bios_flags = &mut __irq_flags as *mut u16;
+    ptr::write_volatile(bios_flags, ptr::read_volatile(bios_flags) | flags);
+
+    if flags & Interrupt::VBlank as u16 != 0 {
+        ptr::write_volatile(&mut FRAMES as *mut u32, FRAMES.wrapping_add(1));
+    }
+
+    for i in 0..SOURCES {
+        if flags & (1 << i) == 0 {
+            continue;
+        }
+        if let Some(handler) = HANDLERS[i] {
+            handler();
+        }
+    }
+}
@@ -15,9 +15,12 @@ extern crate gba;
 
 use core::ptr;
 
+pub mod bios;
+pub mod dma;
 pub mod graphics;
 pub mod header;
 pub mod input;
+pub mod interrupt;
 pub mod memory;
 
 // ARM 32-bit boot code
@@ -47,6 +50,7 @@ pub unsafe extern "C" fn reset() -> ! {
 
     zero_bss(&mut _sbss, &mut _ebss);
     initialize_data(&mut _sdata, &mut _edata, &_sidata);
+    interrupt::init();
 
     main(0, ptr::null());
 
@@ -77,16 +81,16 @@ unsafe fn initialize_data(sdata: *mut u32, edata: *mut u32, sidata: *const u32)
 }
 
 // ARM 32-bit isr code
-// This pushes to the stack then jumps to isr_master in thumb mode
-// And returns from the interrupt following execution
+// This acknowledges pending interrupts and clears IME, then calls isr_master
+// in system mode and returns from the interrupt following execution
 #[cfg(target_os = "none")]
 global_asm!(include_str!("gba_isr.s"));
 
-/// Handle interrupts from a thumb context
+/// Handle the acknowledged interrupts `flags` (IE & IF)
 #[cfg(target_os = "none")]
 #[no_mangle]
-pub unsafe extern "C" fn isr_master() {
-    // TODO: read interrupt registers and handle
+pub unsafe extern "C" fn isr_master(flags: u16) {
+    interrupt::dispatch(flags);
 }
 
 /// ROM header
@@ -36,5 +36,113 @@ pub const REG_DISPCNT: usize = IORAM.0 + 0x0000;
 pub const REG_DISPSTAT: usize = IORAM.0 + 0x0004;
 /// Display scanline counter register
 pub const REG_VCOUNT: usize = IORAM.0 + 0x0006;
+/// Background 0 control register
+pub const REG_BG0CNT: usize = IORAM.0 + 0x0008;
+/// Background 1 control register
+pub const REG_BG1CNT: usize = IORAM.0 + 0x000A;
+/// Background 2 control register
+pub const REG_BG2CNT: usize = IORAM.0 + 0x000C;
+/// Background 3 control register
+pub const REG_BG3CNT: usize = IORAM.0 + 0x000E;
+/// Background 0 horizontal scroll register (write only)
+pub const REG_BG0HOFS: usize = IORAM.0 + 0x0010;
+/// Background 0 vertical scroll register (write only)
+pub const REG_BG0VOFS: usize = IORAM.0 + 0x0012;
+/// Background 1 horizontal scroll register (write only)
+pub const REG_BG1HOFS: usize = IORAM.0 + 0x0014;
+/// Background 1 vertical scroll register (write only)
+pub const REG_BG1VOFS: usize = IORAM.0 + 0x0016;
+/// Background 2 horizontal scroll register (write only)
+pub const REG_BG2HOFS: usize = IORAM.0 + 0x0018;
+/// Background 2 vertical scroll register (write only)
+pub const REG_BG2VOFS: usize = IORAM.0 + 0x001A;
+/// Background 3 horizontal scroll register (write only)
+pub const REG_BG3HOFS: usize = IORAM.0 + 0x001C;
+/// Background 3 vertical scroll register (write only)
+pub const REG_BG3VOFS: usize = IORAM.0 + 0x001E;
+/// Background 2 affine parameter A register (write only)
+pub const REG_BG2PA: usize = IORAM.0 + 0x0020;
+/// Background 2 affine parameter B register (write only)
+pub const REG_BG2PB: usize = IORAM.0 + 0x0022;
+/// Background 2 affine parameter C register (write only)
+pub const REG_BG2PC: usize = IORAM.0 + 0x0024;
+/// Background 2 affine parameter D register (write only)
+pub const REG_BG2PD: usize = IORAM.0 + 0x0026;
+/// Background 2 reference point X register (32-bit, write only)
+pub const REG_BG2X: usize = IORAM.0 + 0x0028;
+/// Background 2 reference point Y register (32-bit, write only)
+pub const REG_BG2Y: usize = IORAM.0 + 0x002C;
+/// Background 3 affine parameter A register (write only)
+pub const REG_BG3PA: usize = IORAM.0 + 0x0030;
+/// Background 3 affine parameter B register (write only)
+pub const REG_BG3PB: usize = IORAM.0 + 0x0032;
+/// Background 3 affine parameter C register (write only)
+pub const REG_BG3PC: usize = IORAM.0 + 0x0034;
+/// Background 3 affine parameter D register (write only)
+pub const REG_BG3PD: usize = IORAM.0 + 0x0036;
+/// Background 3 reference point X register (32-bit, write only)
+pub const REG_BG3X: usize = IORAM.0 + 0x0038;
+/// Background 3 reference point Y register (32-bit, write only)
+pub const REG_BG3Y: usize = IORAM.0 + 0x003C;
+/// Window 0 horizontal dimensions register (write only)
+pub const REG_WIN0H: usize = IORAM.0 + 0x0040;
+/// Window 1 horizontal dimensions register (write only)
+pub const REG_WIN1H: usize = IORAM.0 + 0x0042;
+/// Window 0 vertical dimensions register (write only)
+pub const REG_WIN0V: usize = IORAM.0 + 0x0044;
+/// Window 1 vertical dimensions register (write only)
+pub const REG_WIN1V: usize = IORAM.0 + 0x0046;
+/// Inside of window 0 and 1 control register
+pub const REG_WININ: usize = IORAM.0 + 0x0048;
+/// Outside of windows and object window control register
+pub const REG_WINOUT: usize = IORAM.0 + 0x004A;
+/// Mosaic size register (write only)
+pub const REG_MOSAIC: usize = IORAM.0 + 0x004C;
+/// Colour special effects (blend) control register
+pub const REG_BLDCNT: usize = IORAM.0 + 0x0050;
+/// Alpha blending coefficients register
+pub const REG_BLDALPHA: usize = IORAM.0 + 0x0052;
+/// Brightness (fade) coefficient register (write only)
+pub const REG_BLDY: usize = IORAM.0 + 0x0054;
+/// DMA 0 source address register (32-bit, write only)
+pub const REG_DMA0SAD: usize = IORAM.0 + 0x00B0;
+/// DMA 0 destination address register (32-bit, write only)
+pub const REG_DMA0DAD: usize = IORAM.0 + 0x00B4;
+/// DMA 0 word count register (write only)
+pub const REG_DMA0CNT_L: usize = IORAM.0 + 0x00B8;
+/// DMA 0 control register
+pub const REG_DMA0CNT_H: usize = IORAM.0 + 0x00BA;
+/// DMA 1 source address register (32-bit, write only)
+pub const REG_DMA1SAD: usize = IORAM.0 + 0x00BC;
+/// DMA 1 destination address register (32-bit, write only)
+pub const REG_DMA1DAD: usize = IORAM.0 + 0x00C0;
+/// DMA 1 word count register (write only)
+pub const REG_DMA1CNT_L: usize = IORAM.0 + 0x00C4;
+/// DMA 1 control register
+pub const REG_DMA1CNT_H: usize = IORAM.0 + 0x00C6;
+/// DMA 2 source address register (32-bit, write only)
+pub const REG_DMA2SAD: usize = IORAM.0 + 0x00C8;
+/// DMA 2 destination address register (32-bit, write only)
+pub const REG_DMA2DAD: usize = IORAM.0 + 0x00CC;
+/// DMA 2 word count register (write only)
+pub const REG_DMA2CNT_L: usize = IORAM.0 + 0x00D0;
+/// DMA 2 control register
+pub const REG_DMA2CNT_H: usize = IORAM.0 + 0x00D2;
+/// DMA 3 source address register (32-bit, write only)
+pub const REG_DMA3SAD: usize = IORAM.0 + 0x00D4;
+/// DMA 3 destination address register (32-bit, write only)
+pub const REG_DMA3DAD: usize = IORAM.0 + 0x00D8;
+/// DMA 3 word count register (write only)
+pub const REG_DMA3CNT_L: usize = IORAM.0 + 0x00DC;
+/// DMA 3 control register
+pub const REG_DMA3CNT_H: usize = IORAM.0 + 0x00DE;
 /// Key input register
 pub const REG_KEYINPUT: usize = IORAM.0 + 0x0130;
+/// Key interrupt control register
+pub const REG_KEYCNT: usize = IORAM.0 + 0x0132;
+/// Interrupt enable register
+pub const REG_IE: usize = IORAM.0 + 0x0200;
+/// Interrupt request flags register (write 1 to acknowledge)
+pub const REG_IF: usize = IORAM.0 + 0x0202;
+/// Interrupt master enable register
+pub const REG_IME: usize = IORAM.0 + 0x0208;